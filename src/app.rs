@@ -1,12 +1,22 @@
-use std::error;
+use std::{collections::HashMap, error, ops::Range};
 
+use chrono::Utc;
+use ratatui::text::Text;
 
 
-use crate::article::Article;
+use tokio::sync::mpsc;
+
+use crate::{article::Article, config::Config, event::NetworkEvent, feedloader::{Feed, FeedError}, graphics::{detect_protocol, Graphics}, render::{line_text, render_html, ImageSlot}, search::Search, store::{remove_saved, save_articles, write_saved}};
 
 /// Application result type.
 pub type AppResult<T> = std::result::Result<T, Box<dyn error::Error>>;
 
+/// Rows kept visible above and below the selected headline.
+const SCROLLOFF: usize = 3;
+
+/// Columns taken by the sidebar when it is open.
+const SIDEBAR_WIDTH: usize = 30;
+
 
 #[derive(Debug)]
 pub struct AppArea {
@@ -18,16 +28,127 @@ pub struct AppArea {
 #[derive(Debug, Clone)]
 pub struct AppDetail {
    pub article: Article,
-   pub content: String, 
-   pub scroll_index: usize
+   /// Page being shown, the article's link or a link followed from it.
+   pub url: String,
+   /// Source of the page, empty while loading or if the download failed.
+   pub html: String,
+   /// The page rendered for the width of the detail view.
+   pub content: Text<'static>, 
+   /// Links of the page, shown as footnote `[n]` for `links[n - 1]`.
+   pub links: Vec<String>,
+   /// Line of the content where each link is.
+   pub link_lines: Vec<usize>,
+   /// Space left in the content for images.
+   pub images: Vec<ImageSlot>,
+   /// Link picked with Tab or by typing its number, opened on Enter.
+   pub selected_link: Option<usize>,
+   /// Link number being typed.
+   pub link_input: Option<usize>,
+   pub scroll_index: usize,
+   /// The content is a placeholder until the download arrives.
+   pub loading: bool,
+   /// Showing the article text embedded in the feed rather than the web page.
+   pub embedded: bool,
+   /// Pages to return to with Backspace.
+   pub back: Vec<DetailPage>,
+   /// Search within the page, typed after `/`.
+   pub search: Option<Search>,
+   /// The search is being typed.
+   pub searching: bool,
+   /// Line and byte range of every match of the search in `content`.
+   pub hits: Vec<(usize, Range<usize>)>,
+   /// Index into `hits` of the current match.
+   pub hit: usize,
+}
+
+impl AppDetail {
+    /// Opens `article`, loading until its html is set if there is none yet.
+    pub fn new(article: Article, html: Option<String>) -> Self {
+        Self {
+            url: article.link.clone(),
+            article,
+            loading: html.is_none(),
+            embedded: false,
+            html: html.unwrap_or_default(),
+            content: Text::default(),
+            links: Vec::new(),
+            link_lines: Vec::new(),
+            images: Vec::new(),
+            selected_link: None,
+            link_input: None,
+            scroll_index: 0,
+            back: Vec::new(),
+            search: None,
+            searching: false,
+            hits: Vec::new(),
+            hit: 0,
+        }
+    }
+}
+
+impl AppDetail {
+    /// Finds the matches of the search in the rendered page again.
+    pub fn update_hits(&mut self) {
+        self.hits = match &self.search {
+            Some(search) => self
+                .content
+                .lines
+                .iter()
+                .enumerate()
+                .flat_map(|(idx, line)| search.find(&line_text(line)).into_iter().map(move |x| (idx, x)))
+                .collect(),
+            None => Vec::new(),
+        };
+        self.hit = self.hit.min(self.hits.len().saturating_sub(1));
+    }
+
+    /// Makes match `hit` the current one and scrolls to it.
+    pub fn select_hit(&mut self, hit: usize) {
+        let Some((line, _)) = self.hits.get(hit) else { return };
+        self.hit = hit;
+        self.scroll_index = line.saturating_sub(3);
+    }
+}
+
+/// A page left by following a link.
+#[derive(Debug, Clone)]
+pub struct DetailPage {
+    pub url: String,
+    pub html: String,
+    pub scroll_index: usize,
 }
 
 
+/// How the last load of a feed went.
+#[derive(Debug, Clone)]
+pub struct FeedReport {
+    /// Number of articles, or why the feed failed to load.
+    pub status: Result<usize, FeedError>,
+    /// When the feed last loaded without error.
+    pub last_success: Option<i64>,
+    /// Why entries of the feed were left out, see [`crate::feedloader::ParsedFeed::skipped`].
+    pub skipped: Vec<String>,
+}
+
+/// Which articles the headline table shows, picked in the sidebar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scope {
+    All,
+    /// Articles of the feed with this url.
+    Feed(String),
+    /// Articles of the feeds with this tag.
+    Tag(String),
+}
+
 #[derive(Debug)]
 pub enum AppState {
     Normal, 
-    Detail(AppDetail),
-    Jump(usize)
+    Detail(Box<AppDetail>),
+    Jump(usize),
+    /// Typing a headline search, the headlines are filtered as you type.
+    Search,
+    /// Status of every feed.
+    Feeds
 }
 
 /// Application.
@@ -35,32 +156,348 @@ pub enum AppState {
 pub struct App {
     pub running: bool,
     pub articles: Vec<Article>,
+    /// `articles` changed since they were last written to disk, see [`App::save_history`].
+    pub history_changed: bool,
+    /// Write of the history running in the background.
+    history_write: Option<tokio::task::JoinHandle<()>>,
+    /// Indices into `articles` of the rows shown in the headline table.
+    pub view: Vec<usize>,
+    /// Index into `view` of the selected row.
     pub selected_article_index: usize, 
+    /// First row of `view` shown in the headline table.
+    pub headline_offset: usize,
+    /// Number of rows the headline table had room for when last drawn.
+    pub headline_height: usize,
+    pub unread_only: bool,
+    /// Only show starred articles.
+    pub saved_only: bool,
+    /// Feed or tag the headlines are limited to.
+    pub scope: Scope,
+    /// Show the sidebar listing feeds and tags, toggled with `b`.
+    pub sidebar: bool,
+    /// Show the selected article's summary next to the headlines, toggled with `p`.
+    pub preview: bool,
+    /// Rendered summary of the previewed article, by article key and width.
+    pub preview_cache: Option<((String, usize), Text<'static>)>,
     pub area: AppArea,
     pub last_update_timestamp: i64,
-    pub mode: AppState 
+    pub mode: AppState, 
+    pub config: Config,
+    /// Requests for the network task, see [`crate::event::NetworkHandler`].
+    pub network: mpsc::UnboundedSender<NetworkEvent>,
+    /// Number of network requests that have not been answered yet.
+    pub loading: usize,
+    /// When each feed (by url) was last requested.
+    pub feed_refreshed: HashMap<String, i64>,
+    /// Refresh interval in minutes asked for by each feed (by url).
+    pub feed_ttl: HashMap<String, u64>,
+    /// Result of the last load of each feed (by url).
+    pub feed_reports: HashMap<String, FeedReport>,
+    /// Last headline search, highlighted in the headlines and stepped through with n/N.
+    pub search: Option<Search>,
+    /// Error shown in the status bar, with when it happened.
+    pub message: Option<(String, i64)>,
+    /// Show images in articles, toggled with `i`.
+    pub show_images: bool,
+    pub graphics: Graphics,
 }
 
-impl Default for App {
-    fn default() -> Self {
+impl App {
+    pub fn new(config: Config, network: mpsc::UnboundedSender<NetworkEvent>) -> Self {
         Self {
             running: true,
             articles: Vec::new(),
+            history_changed: false,
+            history_write: None,
+            view: Vec::new(),
             selected_article_index: 0,
+            headline_offset: 0,
+            headline_height: 0,
+            unread_only: false,
+            saved_only: false,
+            scope: Scope::All,
+            sidebar: false,
+            preview: false,
+            preview_cache: None,
             mode: AppState::Normal,
             area: AppArea { width: 0, height: 0 }, 
             last_update_timestamp: 0,
+            network,
+            loading: 0,
+            feed_refreshed: HashMap::new(),
+            feed_ttl: HashMap::new(),
+            feed_reports: HashMap::new(),
+            search: None,
+            message: None,
+            show_images: config.settings.images,
+            graphics: Graphics::new(config.settings.image_protocol.unwrap_or_else(detect_protocol)),
+            config,
         }
     }
-}
 
-impl App {
-    pub fn new() -> Self {
-        Self::default()
+    /// Sends a request to the network task. The answer comes back through the main loop.
+    pub fn request(&mut self, request: NetworkEvent) {
+        if self.network.send(request).is_ok() {
+            self.loading += 1;
+        }
+    }
+
+    /// Shows `message` in the status bar for a while.
+    pub fn show_message(&mut self, message: String) {
+        self.message = Some((message, Utc::now().timestamp()));
+    }
+
+    /// Asks for the images of the open page that have not been requested yet.
+    pub fn request_images(&mut self) {
+        let AppState::Detail(dtl) = &self.mode else { return };
+        let urls: Vec<String> = dtl
+            .images
+            .iter()
+            .filter(|x| !self.graphics.images.contains_key(&x.url))
+            .map(|x| x.url.clone())
+            .collect();
+        for url in urls {
+            self.graphics.images.insert(url.clone(), None);
+            self.request(NetworkEvent::RequestImage(url));
+        }
+    }
+
+    /// Recomputes which articles are shown in the headline table, keeping the
+    /// selected article selected if it is still shown.
+    pub fn update_view(&mut self) {
+        let selected = self.view.get(self.selected_article_index).copied();
+        let filter = match (&self.mode, &self.search) {
+            (AppState::Search, Some(search)) if search.pattern.is_some() => Some(search),
+            _ => None,
+        };
+        self.view = self
+            .articles
+            .iter()
+            .enumerate()
+            .filter(|(_, article)| !self.unread_only || !article.read)
+            .filter(|(_, article)| !self.saved_only || article.starred)
+            .filter(|(_, article)| self.in_scope(&self.scope, article))
+            .filter(|(_, article)| filter.is_none_or(|x| x.matches_article(article)))
+            .map(|(idx, _)| idx)
+            .collect();
+        self.selected_article_index = selected
+            .and_then(|selected| self.view.iter().position(|&idx| idx == selected))
+            .unwrap_or(self.selected_article_index)
+            .min(self.view.len().saturating_sub(1));
+    }
+
+    /// Moves the selection to the next (or previous) headline matching the search.
+    pub fn select_match(&mut self, forward: bool) {
+        let Some(search) = &self.search else { return };
+        let count = self.view.len();
+        let found = (1..count)
+            .map(|step| if forward { (self.selected_article_index + step) % count } else { (self.selected_article_index + count - step) % count })
+            .find(|&position| search.matches_article(&self.articles[self.view[position]]));
+        if let Some(position) = found {
+            self.selected_article_index = position;
+        }
+    }
+
+    /// Moves the selection to the article with `key`, if it is shown.
+    pub fn select_article(&mut self, key: &str) {
+        if let Some(position) = self.view.iter().position(|&idx| self.articles[idx].key() == key) {
+            self.selected_article_index = position;
+        }
+    }
+
+    /// Name of `feed` in the sidebar and feed list: its configured name, else the
+    /// publisher its articles came with, else its url.
+    pub fn feed_name(&self, feed: &Feed) -> String {
+        feed.name.clone()
+            .or_else(|| self.articles.iter().find(|x| x.feed == feed.url).map(|x| x.publisher.clone()))
+            .unwrap_or(feed.url.clone())
+    }
+
+    /// Entries of the sidebar: everything, then every enabled feed, then every tag.
+    pub fn scopes(&self) -> Vec<Scope> {
+        let feeds = self.config.enabled_feeds();
+        let mut tags: Vec<String> = feeds.iter().flat_map(|x| x.tags.clone()).collect();
+        tags.sort();
+        tags.dedup();
+        std::iter::once(Scope::All)
+            .chain(feeds.into_iter().map(|x| Scope::Feed(x.url)))
+            .chain(tags.into_iter().map(Scope::Tag))
+            .collect()
+    }
+
+    pub fn scope_name(&self, scope: &Scope) -> String {
+        match scope {
+            Scope::All => "All".to_string(),
+            Scope::Feed(url) => match self.config.feeds.iter().find(|x| &x.url == url) {
+                Some(feed) => self.feed_name(feed),
+                None => url.clone(),
+            },
+            Scope::Tag(tag) => format!("#{tag}"),
+        }
+    }
+
+    pub fn in_scope(&self, scope: &Scope, article: &Article) -> bool {
+        match scope {
+            Scope::All => true,
+            Scope::Feed(url) => &article.feed == url,
+            Scope::Tag(tag) => self.config.feeds.iter().any(|x| x.url == article.feed && x.tags.contains(tag)),
+        }
+    }
+
+    /// Number of unread articles in `scope`.
+    pub fn unread_count(&self, scope: &Scope) -> usize {
+        self.articles.iter().filter(|x| !x.read && self.in_scope(scope, x)).count()
+    }
+
+    /// Limits the headlines to the sidebar entry `step` entries after (or before) the current one.
+    pub fn step_scope(&mut self, step: i64) {
+        let scopes = self.scopes();
+        let current = scopes.iter().position(|x| x == &self.scope).unwrap_or(0) as i64;
+        let next = (current + step).clamp(0, scopes.len() as i64 - 1) as usize;
+        self.scope = scopes[next].clone();
+        self.selected_article_index = 0;
+        self.headline_offset = 0;
+        self.update_view();
+    }
+
+    /// Columns taken by the sidebar, `0` when it is closed.
+    pub fn sidebar_width(&self) -> usize {
+        if self.sidebar { SIDEBAR_WIDTH.min(self.area.width / 3) } else { 0 }
+    }
+
+    /// Moves the selection by `delta` rows, stopping at the first and last headline.
+    pub fn move_selection(&mut self, delta: i64) {
+        let last = self.view.len().saturating_sub(1) as i64;
+        self.selected_article_index = (self.selected_article_index as i64 + delta).clamp(0, last.max(0)) as usize;
+    }
+
+    /// Scrolls the headline table, `height` rows high, just enough to keep the
+    /// selection `SCROLLOFF` rows away from its edges.
+    pub fn scroll_headlines(&mut self, height: usize) {
+        self.headline_height = height;
+        if height == 0 {
+            return;
+        }
+        let margin = SCROLLOFF.min((height - 1) / 2);
+        let selected = self.selected_article_index;
+        if selected < self.headline_offset + margin {
+            self.headline_offset = selected.saturating_sub(margin);
+        } else if selected + margin >= self.headline_offset + height {
+            self.headline_offset = selected + margin + 1 - height;
+        }
+        self.headline_offset = self.headline_offset.min(self.view.len().saturating_sub(height));
+    }
+
+    pub fn selected_article(&self) -> Option<&Article> {
+        self.view.get(self.selected_article_index).map(|&idx| &self.articles[idx])
+    }
+
+    /// Summary of the selected article rendered for the preview. Kept while the same
+    /// article is shown at the same width, as summaries are often whole articles.
+    pub fn preview_summary(&mut self, width: usize) -> Option<Text<'static>> {
+        let article = self.selected_article()?;
+        let key = (article.key(), width);
+        if self.preview_cache.as_ref().map(|x| &x.0) != Some(&key) {
+            let text = render_html(&article.summary, width);
+            self.preview_cache = Some((key, text));
+        }
+        self.preview_cache.as_ref().map(|x| x.1.clone())
+    }
+
+    /// Sets the read state of the rows at `positions` in the view.
+    pub fn set_read(&mut self, positions: std::ops::Range<usize>, read: bool) {
+        let mut changed = false;
+        for position in positions {
+            let Some(&idx) = self.view.get(position) else { continue };
+            changed |= self.articles[idx].read != read;
+            self.articles[idx].read = read;
+        }
+        if changed {
+            self.history_changed = true;
+            self.update_view();
+        }
+    }
+
+    /// Stars or unstars the article with `key`, saving `content` with it so it can be
+    /// read after it is gone from the web.
+    pub fn set_starred(&mut self, key: &str, starred: bool, content: Option<String>) {
+        let Some(article) = self.articles.iter_mut().find(|x| x.key() == key) else { return };
+        article.starred = starred;
+        match content.filter(|_| starred) {
+            Some(content) => article.saved = write_saved(key, &content).is_ok(),
+            None if !starred => {
+                remove_saved(key);
+                article.saved = false;
+            }
+            None => {}
+        }
+        self.history_changed = true;
+        self.update_view();
+    }
+
+    /// Writes the article history to disk in the background if it changed, once the
+    /// previous write is done. Called every tick, so keys don't wait on the disk.
+    pub fn save_history(&mut self) {
+        if !self.history_changed || self.history_write.as_ref().is_some_and(|x| !x.is_finished()) {
+            return;
+        }
+        self.history_changed = false;
+        let articles = self.articles.clone();
+        self.history_write = Some(tokio::task::spawn_blocking(move || {
+            let _ = save_articles(&articles);
+        }));
+    }
+
+    /// Waits for the background write and writes what changed since, before quitting.
+    pub async fn flush_history(&mut self) {
+        if let Some(write) = self.history_write.take() {
+            let _ = write.await;
+        }
+        if self.history_changed {
+            let _ = save_articles(&self.articles);
+        }
+    }
+
+    /// Requests a download of `feeds`, remembering when it was asked for.
+    pub fn load_feeds(&mut self, feeds: Vec<Feed>) {
+        let now = Utc::now().timestamp();
+        for feed in &feeds {
+            self.feed_refreshed.insert(feed.url.clone(), now);
+        }
+        self.request(NetworkEvent::RequestFeedLoad(feeds));
+    }
+
+    /// Minutes between automatic refreshes of `feed`: the configured interval,
+    /// raised to the publisher's ttl. `0` disables them.
+    fn refresh_interval(&self, feed: &Feed) -> u64 {
+        let interval = self.config.refresh_interval(feed);
+        if interval == 0 {
+            return 0;
+        }
+        interval.max(self.feed_ttl.get(&feed.url).copied().unwrap_or(0))
     }
 
     /// Handles the tick event of the terminal.
-    pub async fn tick(&mut self) {}
+    ///
+    /// Saves the article history if it changed and refreshes every feed whose interval
+    /// has passed in the background.
+    pub async fn tick(&mut self) {
+        self.save_history();
+        let now = Utc::now().timestamp();
+        let due: Vec<Feed> = self
+            .config
+            .enabled_feeds()
+            .into_iter()
+            .filter(|feed| {
+                let interval = self.refresh_interval(feed) as i64 * 60;
+                let last = self.feed_refreshed.get(&feed.url).copied().unwrap_or(0);
+                interval != 0 && now - last >= interval
+            })
+            .collect();
+        if !due.is_empty() {
+            self.load_feeds(due);
+        }
+    }
     
    /// Set running to false to quit the application.
     pub fn quit(&mut self) {
@@ -1,9 +1,64 @@
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Article {
+    /// Stable identity: RSS guid, Atom/JSON Feed id, or the link when the feed has neither.
+    pub id: String,
     pub title: String,
     pub summary: String,
     pub link: String,
-    pub date: i64,
+    /// Publish time given by the feed, `None` when it has none that could be read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<i64>,
+    pub author: String,
     pub publisher: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+    /// Page with the discussion of the article.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comments: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enclosures: Vec<Enclosure>,
+    /// When the article was last changed, if the feed says.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated: Option<i64>,
+    /// Url of the feed the article was downloaded from.
+    #[serde(default)]
+    pub feed: String,
+    #[serde(default)]
+    pub read: bool,
+    #[serde(default)]
+    pub starred: bool,
+    /// A copy of the page was saved when it was starred, see [`crate::store::read_saved`].
+    #[serde(default)]
+    pub saved: bool,
+    /// When the article first showed up in a download.
+    #[serde(default)]
+    pub first_seen: i64,
+    /// When the article was last present in its feed.
+    #[serde(default)]
+    pub last_seen: i64,
+}
+
+impl Article {
+    /// Identity of the article. Ids are only unique within one feed, so it includes the feed's url.
+    pub fn key(&self) -> String {
+        format!("{}\n{}", self.feed, self.id)
+    }
+
+    /// Publish time, or when the article first showed up if the feed doesn't say.
+    pub fn time(&self) -> i64 {
+        self.date.unwrap_or(self.first_seen)
+    }
+}
+
+/// A file attached to an article, e.g. a podcast episode.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Enclosure {
+    pub url: String,
+    #[serde(default)]
+    pub mime_type: String,
+    /// Size in bytes, when the feed says.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub length: Option<u64>,
 }
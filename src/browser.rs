@@ -0,0 +1,74 @@
+use std::process::Stdio;
+
+use tokio::process::Command;
+
+/// Splits a command template like a shell would, so `open -a 'Google Chrome'` keeps the
+/// quoted name together, then replaces `{url}` (or `%s`, as used in `$BROWSER`) in it,
+/// appending the url when the template has neither.
+fn fill_template(template: &str, url: &str) -> Vec<String> {
+    let mut command = shell_words::split(template)
+        .unwrap_or_else(|_| template.split_whitespace().map(str::to_string).collect());
+    let mut filled = false;
+    for arg in &mut command {
+        if arg.contains("{url}") || arg.contains("%s") {
+            *arg = arg.replace("{url}", url).replace("%s", url);
+            filled = true;
+        }
+    }
+    if !filled {
+        command.push(url.to_string());
+    }
+    command
+}
+
+/// The command opening `url`: the configured `browser`, then `$BROWSER`, then the
+/// platform's own opener.
+pub fn browser_command(browser: Option<&str>, url: &str) -> Vec<String> {
+    let env = std::env::var("BROWSER").ok();
+    // `$BROWSER` can list several commands separated by `:`, the first one is used
+    let env = env.as_deref().and_then(|x| x.split(':').find(|x| !x.trim().is_empty()));
+    if let Some(template) = browser.or(env).filter(|x| !x.trim().is_empty()) {
+        return fill_template(template, url);
+    }
+    if cfg!(target_os = "macos") {
+        vec!["open".to_string(), url.to_string()]
+    } else if cfg!(windows) {
+        vec!["cmd".to_string(), "/C".to_string(), "start".to_string(), String::new(), url.to_string()]
+    } else {
+        vec!["xdg-open".to_string(), url.to_string()]
+    }
+}
+
+/// Runs `command` without a terminal, waiting for it to exit.
+pub async fn open_in_browser(command: Vec<String>) -> Result<(), String> {
+    let Some((program, args)) = command.split_first() else { return Err("No browser command".to_string()) };
+    let mut process = Command::new(program);
+    process.args(args).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    // Own process group, so signals meant for newsterm don't reach the browser
+    #[cfg(unix)]
+    process.process_group(0);
+    let status = process
+        .status()
+        .await
+        .map_err(|e| format!("Could not run {program}: {e}"))?;
+    if !status.success() {
+        return Err(format!("{program} failed ({status})"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fill_template;
+
+    #[test]
+    fn fills_templates() {
+        let url = "https://example.com/a b";
+        assert_eq!(fill_template("firefox", url), ["firefox", url]);
+        assert_eq!(fill_template("firefox --new-tab {url}", url), ["firefox", "--new-tab", url]);
+        assert_eq!(fill_template("w3m %s", url), ["w3m", url]);
+        assert_eq!(fill_template("open -a 'Google Chrome' {url}", url), ["open", "-a", "Google Chrome", url]);
+        assert_eq!(fill_template("open -a \"Google Chrome\"", url), ["open", "-a", "Google Chrome", url]);
+        assert_eq!(fill_template("browser --url={url}", url), ["browser", &format!("--url={url}")]);
+    }
+}
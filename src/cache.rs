@@ -0,0 +1,70 @@
+use std::{path::PathBuf, time::{Duration, SystemTime}};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::app::AppResult;
+
+/// FNV-1a, used to turn urls into file names. Unlike `DefaultHasher` it is
+/// stable across Rust versions, so cache entries survive upgrades.
+pub fn hash(key: &str) -> u64 {
+    key.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Directory for one kind of cache entry under `$XDG_CACHE_HOME/newsterm`.
+pub fn cache_dir(kind: &str) -> PathBuf {
+    let mut path = dirs::cache_dir().expect("Couldn't find cache dir");
+    path.push("newsterm");
+    path.push(kind);
+    path
+}
+
+fn cache_path(kind: &str, key: &str) -> PathBuf {
+    cache_dir(kind).join(format!("{:016x}.json", hash(key)))
+}
+
+pub fn has_cache(kind: &str, key: &str) -> bool {
+    cache_path(kind, key).exists()
+}
+
+pub fn read_cache<T: DeserializeOwned>(kind: &str, key: &str) -> Option<T> {
+    let content = std::fs::read_to_string(cache_path(kind, key)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn write_cache<T: Serialize>(kind: &str, key: &str, value: &T) -> AppResult<()> {
+    std::fs::create_dir_all(cache_dir(kind))?;
+    std::fs::write(cache_path(kind, key), serde_json::to_string(value)?)?;
+    Ok(())
+}
+
+/// Deletes entries older than `max_age`, then the oldest ones until the entries
+/// take up at most `max_bytes`. Either limit is ignored when `0`. Entries that
+/// can't be read or deleted are skipped.
+pub fn evict_cache(kind: &str, max_bytes: u64, max_age: Duration) {
+    let Ok(dir) = std::fs::read_dir(cache_dir(kind)) else { return };
+    let now = SystemTime::now();
+    let mut entries = Vec::new();
+    for entry in dir.flatten() {
+        let Ok(metadata) = entry.metadata() else { continue };
+        let Ok(modified) = metadata.modified() else { continue };
+        let age = now.duration_since(modified).unwrap_or_default();
+        if !max_age.is_zero() && age > max_age {
+            let _ = std::fs::remove_file(entry.path());
+        } else {
+            entries.push((modified, metadata.len(), entry.path()));
+        }
+    }
+
+    let mut size: u64 = entries.iter().map(|x| x.1).sum();
+    entries.sort_by_key(|x| x.0);
+    for (_, len, path) in entries {
+        if max_bytes == 0 || size <= max_bytes {
+            break;
+        }
+        if std::fs::remove_file(path).is_ok() {
+            size -= len;
+        }
+    }
+}
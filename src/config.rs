@@ -0,0 +1,193 @@
+use std::path::PathBuf;
+
+use dirs::home_dir;
+use serde::{Deserialize, Serialize};
+use toml_edit::{ArrayOfTables, DocumentMut};
+
+use crate::{app::AppResult, feedloader::Feed, graphics::Protocol};
+
+/// Global settings, applied to every feed unless the feed overrides them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Minutes between automatic refreshes of a feed. `0` only refreshes with `r`.
+    pub refresh_interval: u64,
+    /// Articles older than this many days are hidden. `0` keeps everything.
+    pub max_age: u64,
+    /// Days to remember articles that have dropped off their feed. `0` keeps them forever.
+    pub keep_days: u64,
+    /// Open articles with the text embedded in the feed instead of downloading the web page.
+    /// Off by default, as many feeds only carry a teaser; turned on per feed with `feed_content`.
+    pub feed_content: bool,
+    /// Unread articles to download in the background after each refresh. `0` turns prefetching off.
+    pub prefetch: usize,
+    /// Size limit of the reading cache in megabytes. `0` is unlimited.
+    pub article_cache_mb: u64,
+    /// Days to keep pages in the reading cache. `0` keeps them until the size limit is hit.
+    pub article_cache_days: u64,
+    /// Show images in articles.
+    pub images: bool,
+    /// How to draw images, detected from the terminal when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_protocol: Option<Protocol>,
+    /// Command opening links with `o`, `{url}` is replaced by the link.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub browser: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            refresh_interval: 30,
+            max_age: 0,
+            keep_days: 30,
+            feed_content: false,
+            prefetch: 0,
+            article_cache_mb: 100,
+            article_cache_days: 7,
+            images: true,
+            image_protocol: None,
+            browser: None,
+        }
+    }
+}
+
+/// Contents of `~/.config/newsterm/config.toml`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub settings: Settings,
+    #[serde(default)]
+    pub feeds: Vec<Feed>,
+}
+
+fn config_dir() -> PathBuf {
+    let mut path: PathBuf = home_dir().expect("Couldn't find home dir");
+    path.push(".config/newsterm");
+    path
+}
+
+pub fn config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+fn legacy_feeds_path() -> PathBuf {
+    config_dir().join("feeds")
+}
+
+/// Parses the old one-url-per-line `feeds` file, skipping blanks and `#` comments.
+fn parse_legacy_feeds(content: &str) -> Vec<Feed> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(Feed::new)
+        .collect()
+}
+
+/// Adds `feeds` to the end of a config file as new `[[feeds]]` tables, leaving the
+/// rest of it, comments included, as it is.
+fn append_feeds(content: &str, feeds: &[Feed]) -> AppResult<String> {
+    let mut document: DocumentMut = content.parse()?;
+    let tables = document
+        .entry("feeds")
+        .or_insert(ArrayOfTables::new().into())
+        .as_array_of_tables_mut()
+        .ok_or("`feeds` in config.toml is not a list of [[feeds]] tables")?;
+    for feed in feeds {
+        let table: DocumentMut = toml::to_string(feed)?.parse()?;
+        tables.push(table.as_table().clone());
+    }
+    Ok(document.to_string())
+}
+
+impl Config {
+    /// Loads `config.toml`, migrating the legacy `feeds` file to it if that is all there is.
+    pub fn load() -> AppResult<Self> {
+        let path = config_path();
+        if path.exists() {
+            let content = std::fs::read_to_string(&path)?;
+            return Ok(toml::from_str(&content)?);
+        }
+
+        let legacy_path = legacy_feeds_path();
+        if !legacy_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let config = Self {
+            settings: Settings::default(),
+            feeds: parse_legacy_feeds(&std::fs::read_to_string(legacy_path)?),
+        };
+        config.save()?;
+        Ok(config)
+    }
+
+    pub fn save(&self) -> AppResult<()> {
+        std::fs::create_dir_all(config_dir())?;
+        std::fs::write(config_path(), toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Adds `feeds` to `config.toml` without rewriting the rest of it, see [`append_feeds`].
+    pub fn append_feeds(feeds: &[Feed]) -> AppResult<()> {
+        let path = config_path();
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        std::fs::create_dir_all(config_dir())?;
+        std::fs::write(path, append_feeds(&content, feeds)?)?;
+        Ok(())
+    }
+
+    pub fn enabled_feeds(&self) -> Vec<Feed> {
+        self.feeds.iter().filter(|f| f.enabled).cloned().collect()
+    }
+
+    /// Refresh interval of `feed` in minutes, `0` meaning only refresh with `r`.
+    pub fn refresh_interval(&self, feed: &Feed) -> u64 {
+        feed.refresh_interval.unwrap_or(self.settings.refresh_interval)
+    }
+
+    /// Maximum article age of `feed` in days, `0` meaning unlimited.
+    pub fn max_age(&self, feed: &Feed) -> u64 {
+        feed.max_age.unwrap_or(self.settings.max_age)
+    }
+
+    /// Whether articles of the feed at `url` open with the text embedded in the feed.
+    pub fn feed_content(&self, url: &str) -> bool {
+        self.feeds
+            .iter()
+            .find(|f| f.url == url)
+            .and_then(|f| f.feed_content)
+            .unwrap_or(self.settings.feed_content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appending_feeds_keeps_comments_and_settings() {
+        let content = "# Team feeds\n[[feeds]]\nurl = \"https://a.example/rss\" # the good one\n\n[settings]\n# quick\nrefresh_interval = 10\n";
+        let mut feed = Feed::new("https://b.example/rss");
+        feed.tags = vec!["Tech".to_string()];
+
+        let appended = append_feeds(content, &[feed]).unwrap();
+        assert_eq!(
+            appended,
+            "# Team feeds\n[[feeds]]\nurl = \"https://a.example/rss\" # the good one\n\n\
+             [[feeds]]\nurl = \"https://b.example/rss\"\ntags = [\"Tech\"]\nenabled = true\n\n\
+             [settings]\n# quick\nrefresh_interval = 10\n"
+        );
+        let config: Config = toml::from_str(&appended).unwrap();
+        assert_eq!(config.feeds.len(), 2);
+        assert_eq!(config.settings.refresh_interval, 10);
+
+        assert!(append_feeds("", &[Feed::new("https://c.example/rss")]).unwrap().starts_with("[[feeds]]\nurl"));
+        assert!(append_feeds("feeds = []\n", &[Feed::new("https://c.example/rss")]).is_err());
+    }
+}
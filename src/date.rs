@@ -0,0 +1,192 @@
+//! Lenient parsing of the dates found in feeds.
+//!
+//! RSS asks for RFC 2822 dates, but feeds in the wild leave out the weekday, use
+//! timezone names like "EST", write ISO 8601 dates or drop the time altogether.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+/// Offsets of the timezone names seen in feeds, RFC 2822 only knows the US ones.
+const TIMEZONES: &[(&str, &str)] = &[
+    ("UTC", "+0000"),
+    ("UT", "+0000"),
+    ("GMT", "+0000"),
+    ("Z", "+0000"),
+    ("EST", "-0500"),
+    ("EDT", "-0400"),
+    ("CST", "-0600"),
+    ("CDT", "-0500"),
+    ("MST", "-0700"),
+    ("MDT", "-0600"),
+    ("PST", "-0800"),
+    ("PDT", "-0700"),
+    ("AKST", "-0900"),
+    ("AKDT", "-0800"),
+    ("HST", "-1000"),
+    ("BST", "+0100"),
+    ("IST", "+0530"),
+    ("WET", "+0000"),
+    ("WEST", "+0100"),
+    ("CET", "+0100"),
+    ("CEST", "+0200"),
+    ("EET", "+0200"),
+    ("EEST", "+0300"),
+    ("MSK", "+0300"),
+    ("JST", "+0900"),
+    ("KST", "+0900"),
+    ("AEST", "+1000"),
+    ("AEDT", "+1100"),
+    ("NZST", "+1200"),
+    ("NZDT", "+1300"),
+];
+
+const WEEKDAYS: &[&str] = &["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// Formats with a timezone, tried after the weekday is dropped and named zones are replaced.
+const ZONED_FORMATS: &[&str] = &[
+    "%d %b %Y %H:%M:%S %z",
+    "%d %b %Y %H:%M %z",
+    "%d %B %Y %H:%M:%S %z",
+    "%d %b %y %H:%M:%S %z",
+    "%b %d %Y %H:%M:%S %z",
+    "%b %d %H:%M:%S %z %Y",
+    "%Y-%m-%dT%H:%M:%S%.f%z",
+    "%Y-%m-%dT%H:%M:%S%.f %z",
+    "%Y-%m-%dT%H:%M%z",
+    "%Y-%m-%d %H:%M:%S%.f %z",
+    "%Y-%m-%d %H:%M:%S%.f%z",
+];
+
+/// Formats without a timezone, read as UTC.
+const NAIVE_FORMATS: &[&str] = &[
+    "%d %b %Y %H:%M:%S",
+    "%d %b %Y %H:%M",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+];
+
+/// Formats without a time, read as midnight UTC.
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%d %b %Y", "%d %B %Y", "%b %d %Y", "%B %d %Y", "%Y/%m/%d"];
+
+/// Unix timestamp of a date in any of the formats feeds use, `None` if it can't be read.
+pub fn parse_date(date: &str) -> Option<i64> {
+    let date = date.trim();
+    if date.is_empty() {
+        return None;
+    }
+    if let Ok(parsed) = DateTime::parse_from_rfc2822(date).or_else(|_| DateTime::parse_from_rfc3339(date)) {
+        return Some(parsed.timestamp());
+    }
+    let date = normalize(date);
+    ZONED_FORMATS
+        .iter()
+        .find_map(|format| DateTime::parse_from_str(&date, format).ok())
+        .map(|x| x.timestamp())
+        .or_else(|| NAIVE_FORMATS.iter().find_map(|format| NaiveDateTime::parse_from_str(&date, format).ok()).map(|x| x.and_utc().timestamp()))
+        .or_else(|| {
+            DATE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(&date, format).ok())
+                .and_then(|x| x.and_hms_opt(0, 0, 0))
+                .map(|x| x.and_utc().timestamp())
+        })
+}
+
+/// Drops the weekday and commas, collapses whitespace and replaces timezone names with offsets.
+fn normalize(date: &str) -> String {
+    let mut words: Vec<String> = date
+        .replace(',', " ")
+        .split_whitespace()
+        .map(str::to_string)
+        .collect();
+    // "Sat", "Saturday", "Thurs"...
+    if words.len() > 1 && WEEKDAYS.iter().any(|x| words[0].to_lowercase().starts_with(x)) {
+        words.remove(0);
+    }
+    // "EST", but also "(EST)" and "GMT+5:30"
+    if let Some(zone) = words.last().map(|x| x.trim_matches(|x| x == '(' || x == ')').to_uppercase()) {
+        let after_offset = words.len() > 1 && is_offset(&words[words.len() - 2]);
+        if after_offset && zone.chars().all(|x| x.is_ascii_alphabetic()) {
+            // "-0700 PDT", the offset already says it all
+            words.pop();
+        } else if let Some(offset) = zone_offset(&zone) {
+            let last = words.len() - 1;
+            words[last] = offset;
+        }
+    }
+    words.join(" ")
+}
+
+/// Whether `word` is a numeric offset, "+0530" or "+05:30".
+fn is_offset(word: &str) -> bool {
+    let Some(digits) = word.strip_prefix('+').or_else(|| word.strip_prefix('-')) else { return false };
+    let digits = digits.replace(':', "");
+    digits.len() == 4 && digits.chars().all(|x| x.is_ascii_digit())
+}
+
+/// Offset of a timezone name or of "GMT+2", "GMT+0530" and "UTC+5:30", as "+hhmm".
+fn zone_offset(zone: &str) -> Option<String> {
+    if let Some((_, offset)) = TIMEZONES.iter().find(|(name, _)| *name == zone) {
+        return Some(offset.to_string());
+    }
+    let offset = zone.strip_prefix("GMT").or_else(|| zone.strip_prefix("UTC"))?;
+    let (sign, offset) = match offset.split_at_checked(1)? {
+        ("+", offset) => ('+', offset),
+        ("-", offset) => ('-', offset),
+        _ => return None,
+    };
+    let (hours, minutes) = match offset.split_once(':') {
+        Some(parts) => parts,
+        None if offset.len() > 2 => offset.split_at(offset.len() - 2),
+        None => (offset, "0"),
+    };
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    (hours < 24 && minutes < 60).then(|| format!("{sign}{hours:02}{minutes:02}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_date;
+
+    #[test]
+    fn parses_dates_from_feeds() {
+        let dates = [
+            ("Sat, 17 Oct 2026 10:00:00 GMT", 1792231200),
+            ("Sat, 17 Oct 2026 10:00:00 +0000", 1792231200),
+            ("  Sat, 17 Oct 2026 10:00:00 Z  ", 1792231200),
+            ("17 Oct 2026 08:00:00 EST", 1792242000),
+            ("Saturday, 17 Oct 2026 08:00 CEST", 1792216800),
+            ("Sat, 17 Oct 2026 08:00:00 CEST", 1792216800),
+            ("Thu, 5 Jun 2025 10:00:00 -0700 PDT", 1749142800),
+            ("Thu, 05 Jun 2025 10:00:00 -0700 (PDT)", 1749142800),
+            ("Thu, 05 Jun 2025 10:00:00 PDT", 1749142800),
+            ("Sat, 17 Oct 2026 10:00:00 GMT+0530", 1792211400),
+            ("2026-10-17 10:00:00 UTC+5:30", 1792211400),
+            ("Sat, 17 Oct 2026 10:00:00 GMT-8", 1792260000),
+            ("Sat, 17 Oct 26 10:00:00 GMT", 1792231200),
+            ("Sat Oct 17 08:00:00 +0000 2026", 1792224000),
+            ("2026-10-17T08:00:00Z", 1792224000),
+            ("2026-10-17T10:00:00.123+02:00", 1792224000),
+            ("2026-10-17T10:00:00+0200", 1792224000),
+            ("2026-10-17 10:00:00 +02:00", 1792224000),
+            ("2026-10-17T08:00:00", 1792224000),
+            ("2026-10-17T08:00", 1792224000),
+            ("2026-10-17", 1792195200),
+            ("Oct 17, 2026", 1792195200),
+            ("October 17, 2026", 1792195200),
+            ("17 October 2026", 1792195200),
+        ];
+        for (date, expected) in dates {
+            assert_eq!(parse_date(date), Some(expected), "{date}");
+        }
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_date() {
+        for date in ["", "   ", "garbage", "yesterday", "2026-13-45", "Sat, 32 Oct 2026 10:00:00 GMT", "Sat, 17 Oct 2026 10:00:00 GMT+25"] {
+            assert_eq!(parse_date(date), None, "{date}");
+        }
+    }
+}
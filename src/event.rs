@@ -2,9 +2,10 @@ use std::time::Duration;
 
 use crossterm::event::{Event as CrosstermEvent, KeyEvent, MouseEvent};
 use futures::{FutureExt, StreamExt};
+use image::DynamicImage;
 use tokio::sync::mpsc;
 
-use crate::{app::{AppDetail, AppResult}, article::Article};
+use crate::{app::AppResult, article::Article, browser::open_in_browser, feedloader::{Feed, LoadedFeed, download_article, download_image, download_page, load_feeds, prefetch_articles}};
 
 /// Terminal events.
 #[derive(Clone, Debug)]
@@ -20,41 +21,110 @@ pub enum Event {
 
 }
 
+/// Network requests sent by the app, and the results sent back to the main loop.
 #[derive(Clone, Debug)]
 pub enum NetworkEvent {
     RequestArticleDetail(Article),
-    RecieveArticleDetail(AppDetail),
-    RequestFeedLoad, 
-    FeedLoaded(Vec<Article>)
+    /// The html of the article page, or `None` if it could not be downloaded.
+    RecieveArticleDetail(Article, Option<String>),
+    RequestFeedLoad(Vec<Feed>), 
+    FeedLoaded(Vec<LoadedFeed>),
+    /// A link followed from the detail view.
+    RequestPage(String),
+    RecievePage(String, Option<String>),
+    /// Evicts pages over the size limit (bytes) or older than the age limit from the
+    /// reading cache, then downloads articles into it. Nothing is sent back.
+    Prefetch(Vec<Article>, u64, Duration),
+    RequestImage(String),
+    RecieveImage(String, Option<DynamicImage>),
+    /// Runs a browser command, only answered with `BrowserFailed` if it doesn't work.
+    OpenBrowser(Vec<String>),
+    BrowserFailed(String),
 }
 
+/// Network event handler.
+///
+/// Requests sent through [`NetworkHandler::sender`] are handled on their own tasks
+/// so slow downloads never block the UI.
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct NetworkHandler {
+    /// Request sender channel.
     sender: mpsc::UnboundedSender<NetworkEvent>,
+    /// Result receiver channel.
     receiver: mpsc::UnboundedReceiver<NetworkEvent>,
+    /// Request handler thread.
+    handler: tokio::task::JoinHandle<()>,
+}
+
+impl Default for NetworkHandler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl NetworkHandler {
+    /// Constructs a new instance of [`NetworkHandler`].
     pub fn new() -> Self {
-        let (sender, receiver) = mpsc::unbounded_channel();
-        let _sender = sender.clone();
+        let (sender, mut requests) = mpsc::unbounded_channel();
+        let (_sender, receiver) = mpsc::unbounded_channel();
+        let handler = tokio::spawn(async move {
+            while let Some(request) = requests.recv().await {
+                let _sender = _sender.clone();
+                tokio::spawn(async move {
+                    let result = match request {
+                        NetworkEvent::RequestFeedLoad(feeds) => {
+                            NetworkEvent::FeedLoaded(load_feeds(feeds).await)
+                        }
+                        NetworkEvent::RequestArticleDetail(article) => {
+                            let html = download_article(&article).await;
+                            NetworkEvent::RecieveArticleDetail(article, html)
+                        }
+                        NetworkEvent::RequestPage(url) => {
+                            let html = download_page(&url).await;
+                            NetworkEvent::RecievePage(url, html)
+                        }
+                        NetworkEvent::Prefetch(articles, max_bytes, max_age) => {
+                            return prefetch_articles(articles, max_bytes, max_age).await
+                        }
+                        NetworkEvent::RequestImage(url) => {
+                            let image = download_image(&url).await;
+                            NetworkEvent::RecieveImage(url, image)
+                        }
+                        NetworkEvent::OpenBrowser(command) => match open_in_browser(command).await {
+                            Ok(()) => return,
+                            Err(e) => NetworkEvent::BrowserFailed(e),
+                        },
+                        NetworkEvent::RecieveArticleDetail(..)
+                        | NetworkEvent::BrowserFailed(_)
+                        | NetworkEvent::FeedLoaded(_)
+                        | NetworkEvent::RecievePage(..)
+                        | NetworkEvent::RecieveImage(..) => return,
+                    };
+                    let _ = _sender.send(result);
+                });
+            }
+        });
 
         Self {
             sender,
             receiver,
+            handler,
         }
     }
 
-   // pub async fn next(&mut self) -> AppResult<NetworkEvent> {
-    //     self.receiver
-    //         .recv()
-    //         .await
-    //         .ok_or(Box::new(std::io::Error::new(
-    //             std::io::ErrorKind::Other,
-    //             "This is a network error",
-    //         )))
-    // }
+    /// Returns a sender for making requests.
+    pub fn sender(&self) -> mpsc::UnboundedSender<NetworkEvent> {
+        self.sender.clone()
+    }
+
+    /// Receive the next network result.
+    pub async fn next(&mut self) -> AppResult<NetworkEvent> {
+        self.receiver
+            .recv()
+            .await
+            .ok_or(Box::new(std::io::Error::other("This is a network error")))
+    }
 }
 
 
@@ -129,9 +199,6 @@ impl EventHandler {
         self.receiver
             .recv()
             .await
-            .ok_or(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "This is an IO error",
-            )))
+            .ok_or(Box::new(std::io::Error::other("This is an IO error")))
     }
 }
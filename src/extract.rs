@@ -0,0 +1,384 @@
+//! Main content extraction, in the spirit of Readability.
+//!
+//! Pages are cleared of navigation, footers, cookie banners, share widgets and
+//! related-article rails, then the node holding most of the article's text is
+//! picked by scoring paragraphs and crediting their ancestors.
+
+use std::collections::{HashMap, HashSet};
+
+use ego_tree::{NodeId, NodeRef};
+use scraper::{Html, Node};
+
+/// Elements that are never part of an article.
+const JUNK_TAGS: &[&str] = &["nav", "aside", "footer", "script", "style", "noscript", "iframe", "form", "button"];
+
+/// Class and id fragments of elements that are rarely part of an article.
+const NEGATIVE: &[&str] = &[
+    "comment", "meta", "footer", "footnote", "nav", "menu", "sidebar", "sponsor", "advert", "share",
+    "social", "related", "promo", "cookie", "consent", "gdpr", "banner", "newsletter", "subscribe",
+    "popup", "modal", "breadcrumb", "widget", "masthead", "outbrain", "taboola", "recommend", "signup",
+    "paywall", "toolbar", "byline-share", "more-stories", "trending",
+];
+
+/// Class and id words too short to look for inside other words.
+const NEGATIVE_WORDS: &[&str] = &["ad", "ads", "adv", "skip", "hidden", "print"];
+
+/// Class and id fragments of elements that usually hold the article.
+const POSITIVE: &[&str] = &["article", "body", "content", "entry", "main", "page", "post", "text", "blog", "story"];
+
+/// Tags whose text is scored as a paragraph.
+const PARAGRAPH_TAGS: &[&str] = &["p", "pre", "td"];
+
+const BLOCK_TAGS: &[&str] = &[
+    "p", "div", "section", "article", "table", "ul", "ol", "blockquote", "pre", "h1", "h2", "h3", "h4",
+    "h5", "h6", "figure", "dl", "form",
+];
+
+/// The parts of a document to show.
+pub struct Extraction {
+    /// Nodes to render, in document order.
+    pub roots: Vec<NodeId>,
+    /// Nodes under the roots to leave out.
+    pub removed: HashSet<NodeId>,
+}
+
+#[derive(Default, Clone, Copy)]
+struct Stats {
+    text: usize,
+    link_text: usize,
+    commas: usize,
+}
+
+impl Stats {
+    fn link_density(&self) -> f64 {
+        if self.text == 0 {
+            0.0
+        } else {
+            self.link_text as f64 / self.text as f64
+        }
+    }
+}
+
+fn element_name<'a>(node: &NodeRef<'a, Node>) -> Option<&'a str> {
+    node.value().as_element().map(|x| x.name())
+}
+
+fn class_and_id(node: &NodeRef<'_, Node>) -> String {
+    let Some(element) = node.value().as_element() else { return String::new() };
+    format!("{} {}", element.attr("class").unwrap_or(""), element.attr("id").unwrap_or("")).to_lowercase()
+}
+
+fn is_negative(names: &str) -> bool {
+    NEGATIVE.iter().any(|x| names.contains(x))
+        || names
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| NEGATIVE_WORDS.contains(&word))
+}
+
+fn is_positive(names: &str) -> bool {
+    POSITIVE.iter().any(|x| names.contains(x))
+}
+
+fn class_weight(node: &NodeRef<'_, Node>) -> f64 {
+    let names = class_and_id(node);
+    let mut weight = 0.0;
+    if is_negative(&names) {
+        weight -= 25.0;
+    }
+    if is_positive(&names) {
+        weight += 25.0;
+    }
+    weight
+}
+
+/// Whether an element is clutter that should go before anything is scored.
+fn is_unlikely(node: &NodeRef<'_, Node>) -> bool {
+    let Some(element) = node.value().as_element() else { return false };
+    let name = element.name();
+    if matches!(name, "html" | "body" | "article" | "main") {
+        return false;
+    }
+    if JUNK_TAGS.contains(&name) || element.attr("hidden").is_some() || element.attr("aria-hidden") == Some("true") {
+        return true;
+    }
+    if matches!(element.attr("role"), Some("navigation" | "complementary" | "banner" | "contentinfo" | "dialog" | "alertdialog")) {
+        return true;
+    }
+    if element.attr("style").is_some_and(|x| x.replace(' ', "").contains("display:none")) {
+        return true;
+    }
+    let names = class_and_id(node);
+    is_negative(&names) && !is_positive(&names)
+}
+
+struct Extractor {
+    removed: HashSet<NodeId>,
+    stats: HashMap<NodeId, Stats>,
+    scores: HashMap<NodeId, f64>,
+}
+
+impl Extractor {
+    fn remove_unlikely(&mut self, node: NodeRef<'_, Node>) {
+        if is_unlikely(&node) {
+            self.removed.insert(node.id());
+            return;
+        }
+        for child in node.children() {
+            self.remove_unlikely(child);
+        }
+    }
+
+    /// Text statistics of every node, leaving out removed ones.
+    fn measure(&mut self, node: NodeRef<'_, Node>) -> Stats {
+        if self.removed.contains(&node.id()) {
+            return Stats::default();
+        }
+        let stats = match node.value() {
+            Node::Text(text) => {
+                let text = text.trim();
+                Stats { text: text.chars().count(), link_text: 0, commas: text.matches(',').count() }
+            }
+            _ => {
+                let mut stats = Stats::default();
+                for child in node.children() {
+                    let child = self.measure(child);
+                    stats.text += child.text;
+                    stats.link_text += child.link_text;
+                    stats.commas += child.commas;
+                }
+                if element_name(&node) == Some("a") {
+                    stats.link_text = stats.text;
+                }
+                stats
+            }
+        };
+        self.stats.insert(node.id(), stats);
+        stats
+    }
+
+    fn stats(&self, node: &NodeRef<'_, Node>) -> Stats {
+        self.stats.get(&node.id()).copied().unwrap_or_default()
+    }
+
+    fn is_paragraph(&self, node: &NodeRef<'_, Node>) -> bool {
+        match element_name(node) {
+            Some(name) if PARAGRAPH_TAGS.contains(&name) => true,
+            // Plenty of sites lay out paragraphs as divs of bare text
+            Some("div" | "section") => !node
+                .children()
+                .any(|x| element_name(&x).is_some_and(|x| BLOCK_TAGS.contains(&x))),
+            _ => false,
+        }
+    }
+
+    fn initial_score(node: &NodeRef<'_, Node>) -> f64 {
+        let base = match element_name(node) {
+            Some("div" | "article" | "main") => 5.0,
+            Some("pre" | "td" | "blockquote") => 3.0,
+            Some("address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form") => -3.0,
+            Some("h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th") => -5.0,
+            _ => 0.0,
+        };
+        base + class_weight(node)
+    }
+
+    /// Credits every paragraph's parent, and to a lesser degree its further ancestors.
+    fn score(&mut self, node: NodeRef<'_, Node>) {
+        if self.removed.contains(&node.id()) {
+            return;
+        }
+        if self.is_paragraph(&node) {
+            let stats = self.stats(&node);
+            if stats.text >= 25 {
+                let score = 1.0 + stats.commas as f64 + (stats.text as f64 / 100.0).min(3.0);
+                for (level, ancestor) in node.ancestors().filter(|x| x.value().is_element()).take(3).enumerate() {
+                    let divider = match level {
+                        0 => 1.0,
+                        1 => 2.0,
+                        level => level as f64 * 3.0,
+                    };
+                    *self.scores.entry(ancestor.id()).or_insert_with(|| Self::initial_score(&ancestor)) += score / divider;
+                }
+            }
+        }
+        for child in node.children() {
+            self.score(child);
+        }
+    }
+
+    /// Removes link lists inside the content, e.g. share bars and related-article rails.
+    fn remove_link_lists(&mut self, node: NodeRef<'_, Node>) {
+        if self.removed.contains(&node.id()) {
+            return;
+        }
+        if matches!(element_name(&node), Some("ul" | "ol" | "div" | "section" | "table")) {
+            let stats = self.stats(&node);
+            if stats.text < 1000 && stats.link_density() > 0.5 {
+                self.removed.insert(node.id());
+                return;
+            }
+        }
+        for child in node.children() {
+            self.remove_link_lists(child);
+        }
+    }
+}
+
+/// Picks out the article body of `document`. Falls back to the whole `<body>`
+/// when nothing looks like an article.
+pub fn extract(document: &Html) -> Extraction {
+    let root = document.tree.root();
+    let body = root
+        .descendants()
+        .find(|x| element_name(x) == Some("body"))
+        .unwrap_or(root);
+
+    let mut extractor = Extractor { removed: HashSet::new(), stats: HashMap::new(), scores: HashMap::new() };
+    extractor.remove_unlikely(body);
+    extractor.measure(body);
+    extractor.score(body);
+
+    let top = extractor
+        .scores
+        .iter()
+        .map(|(&id, &score)| {
+            let node = document.tree.get(id).expect("scored nodes are in the tree");
+            (id, score * (1.0 - extractor.stats(&node).link_density()))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    let Some((top, top_score)) = top.filter(|(id, _)| {
+        let node = document.tree.get(*id).expect("scored nodes are in the tree");
+        extractor.stats(&node).text >= 250
+    }) else {
+        return Extraction { roots: vec![body.id()], removed: extractor.removed };
+    };
+
+    // Siblings often hold more of the story, e.g. paragraphs split around an ad slot
+    let top_node = document.tree.get(top).expect("scored nodes are in the tree");
+    let threshold = (top_score * 0.2).max(10.0);
+    let siblings: Vec<NodeRef<Node>> = match top_node.parent() {
+        Some(parent) if parent.id() != root.id() => parent.children().collect(),
+        _ => vec![top_node],
+    };
+    let roots: Vec<NodeId> = siblings
+        .into_iter()
+        .filter(|sibling| {
+            if sibling.id() == top {
+                return true;
+            }
+            if extractor.removed.contains(&sibling.id()) || !sibling.value().is_element() {
+                return false;
+            }
+            let stats = extractor.stats(sibling);
+            let score = extractor.scores.get(&sibling.id()).copied().unwrap_or(0.0) * (1.0 - stats.link_density());
+            score >= threshold
+                || (element_name(sibling) == Some("p") && stats.text > 80 && stats.link_density() < 0.25)
+        })
+        .map(|x| x.id())
+        .collect();
+
+    for &id in &roots {
+        let node = document.tree.get(id).expect("roots are in the tree");
+        for child in node.children() {
+            extractor.remove_link_lists(child);
+        }
+    }
+    Extraction { roots, removed: extractor.removed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A paragraph long enough, and with enough commas, to score as article text.
+    fn paragraph(topic: &str) -> String {
+        format!("<p>The story about {topic} goes on, and on, with enough words, commas and detail to read like a real paragraph of a news article, which is what the extractor looks for.</p>")
+    }
+
+    /// Text of what the extraction keeps, one line per text node.
+    fn kept_text(html: &str) -> String {
+        fn collect(node: NodeRef<'_, Node>, removed: &HashSet<NodeId>, out: &mut Vec<String>) {
+            if removed.contains(&node.id()) {
+                return;
+            }
+            if let Node::Text(text) = node.value() {
+                if !text.trim().is_empty() {
+                    out.push(text.trim().to_string());
+                }
+            }
+            for child in node.children() {
+                collect(child, removed, out);
+            }
+        }
+
+        let document = Html::parse_document(html);
+        let extraction = extract(&document);
+        let mut out = Vec::new();
+        for id in extraction.roots {
+            collect(document.tree.get(id).unwrap(), &extraction.removed, &mut out);
+        }
+        out.join("\n")
+    }
+
+    #[test]
+    fn clutter_around_the_article_is_removed() {
+        let html = format!(
+            "<html><body>\
+             <nav><a href=\"/\">Home</a> <a href=\"/world\">World</a></nav>\
+             <div class=\"cookie-consent\">We use cookies, to improve, your experience. Accept all?</div>\
+             <div id=\"main\"><article>\
+               <h1>Headline</h1>{}{}\
+               <ul><li><a href=\"/a\">Share on the social network with the long name</a></li><li><a href=\"/b\">Share by email</a></li></ul>\
+               {}\
+             </article>\
+             <div class=\"related-stories\"><h2>Related</h2><a href=\"/c\">Another story, about something else, entirely</a></div></div>\
+             <footer>Copyright, all rights, reserved</footer>\
+             </body></html>",
+            paragraph("one"),
+            paragraph("two"),
+            paragraph("three"),
+        );
+        let text = kept_text(&html);
+        for kept in ["Headline", "about one", "about two", "about three"] {
+            assert!(text.contains(kept), "{kept} missing from {text}");
+        }
+        for removed in ["Home", "cookies", "Share", "Related", "Another story", "Copyright"] {
+            assert!(!text.contains(removed), "{removed} kept in {text}");
+        }
+    }
+
+    #[test]
+    fn paragraphs_split_around_an_ad_are_kept() {
+        let html = format!(
+            "<html><body><div>\
+             <div class=\"story\">{}{}{}</div>\
+             <div class=\"ad-slot\">Advertisement, buy things, now</div>\
+             <div class=\"story\">{}{}</div>\
+             </div></body></html>",
+            paragraph("one"),
+            paragraph("two"),
+            paragraph("three"),
+            paragraph("four"),
+            paragraph("five"),
+        );
+        let text = kept_text(&html);
+        for kept in ["about one", "about three", "about four", "about five"] {
+            assert!(text.contains(kept), "{kept} missing from {text}");
+        }
+        assert!(!text.contains("Advertisement"), "{text}");
+    }
+
+    #[test]
+    fn pages_without_an_article_fall_back_to_the_body() {
+        let html = "<html><head><title>Sign in</title></head><body><h1>Sign in</h1><p>Enter your password.</p><nav>Menu</nav></body></html>";
+        let document = Html::parse_document(html);
+        let extraction = extract(&document);
+        let roots: Vec<Option<&str>> = extraction
+            .roots
+            .iter()
+            .map(|&id| element_name(&document.tree.get(id).unwrap()))
+            .collect();
+        assert_eq!(roots, [Some("body")]);
+        assert_eq!(kept_text(html), "Sign in\nEnter your password.");
+    }
+}
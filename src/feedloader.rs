@@ -1,184 +1,766 @@
-use std::process::{Stdio, Command};
-use std::io::Write;
-use crate::article::Article;
-use chrono::DateTime;
-use futures::future;
-use rss::Channel;
-
-fn parse_rfc_2822_date(date: &str) -> i64 {
-    DateTime::parse_from_rfc2822(date).map_or(0, |d| d.timestamp())
-}
+use std::sync::OnceLock;
+use std::time::Duration;
+use crate::{article::{Article, Enclosure}, date::parse_date, cache::{evict_cache, read_cache, write_cache}};
+use futures::StreamExt;
+use rss::{extension::syndication::UpdatePeriod, Channel};
+use serde::{Deserialize, Serialize};
 
 /**
  * Feed
 */
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Feed {
     pub url: String,
+    /// Overrides the publisher name taken from the feed itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Minutes between refreshes, overriding `settings.refresh_interval`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_interval: Option<u64>,
+    /// Maximum article age in days, overriding `settings.max_age`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u64>,
+    /// Whether to show the article text embedded in the feed, overriding `settings.feed_content`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feed_content: Option<bool>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Feed {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            name: None,
+            tags: Vec::new(),
+            enabled: true,
+            refresh_interval: None,
+            max_age: None,
+            feed_content: None,
+        }
+    }
 }
 
 /**
  * Downloader
 */
-async fn download_link(url: &str) -> Option<String> {
-    let response = reqwest::get(url).await.map_or(None, Some)?;
-    let text = response.text().await.map_or(None, Some)?;
-    Some(text)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedResponse {
+    pub content_type: Option<String>,
+    pub body: String,
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+}
+
+const FEED_CACHE: &str = "feeds";
+/// Article pages, keyed by [`Article::key`].
+pub const ARTICLE_CACHE: &str = "articles";
+
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            // Sites often turn away requests without one
+            .user_agent(concat!("newsterm/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+fn header(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|x| x.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Why a feed could not be loaded.
+#[derive(Debug, Clone)]
+pub enum FeedError {
+    /// The server answered with a non-success status code.
+    Http(u16),
+    Timeout,
+    /// Any other request failure, e.g. DNS or a refused connection.
+    Network(String),
+    /// The body was not a feed newsterm understands.
+    Parse(String),
+}
+
+impl std::fmt::Display for FeedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeedError::Http(status) => write!(f, "HTTP {status}"),
+            FeedError::Timeout => write!(f, "timed out"),
+            FeedError::Network(message) => write!(f, "network error: {message}"),
+            FeedError::Parse(message) => write!(f, "parse error: {message}"),
+        }
+    }
+}
+
+impl From<reqwest::Error> for FeedError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            FeedError::Timeout
+        } else {
+            FeedError::Network(error.to_string())
+        }
+    }
+}
+
+/// Downloads a feed with a conditional request against the last good response,
+/// which is kept on disk by `load_feed`. A 304 gives back the cached copy, and so
+/// does a failure, alongside the error.
+async fn download_link(url: &str) -> Result<FeedResponse, (FeedError, Option<FeedResponse>)> {
+    let cached: Option<FeedResponse> = read_cache(FEED_CACHE, url);
+
+    let mut request = client().get(url);
+    if let Some(cached) = &cached {
+        if let Some(etag) = &cached.etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => return Err((e.into(), cached)),
+    };
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        if let Some(cached) = cached {
+            return Ok(cached);
+        }
+    }
+    if !response.status().is_success() {
+        return Err((FeedError::Http(response.status().as_u16()), cached));
+    }
+
+    let content_type = header(&response, reqwest::header::CONTENT_TYPE);
+    let etag = header(&response, reqwest::header::ETAG);
+    let last_modified = header(&response, reqwest::header::LAST_MODIFIED);
+    let body = match response.text().await {
+        Ok(body) => body,
+        Err(e) => return Err((e.into(), cached)),
+    };
+    Ok(FeedResponse { content_type, body, etag, last_modified })
+}
+
+/// A downloaded and parsed feed.
+#[derive(Debug, Clone)]
+pub struct ParsedFeed {
+    pub articles: Vec<Article>,
+    /// Minimum minutes between refreshes requested by the publisher
+    /// through RSS `<ttl>` or `sy:updatePeriod`.
+    pub ttl: Option<u64>,
+    /// Why each entry that could not be turned into an article was left out.
+    pub skipped: Vec<String>,
+}
+
+/// The outcome of loading one feed.
+#[derive(Debug, Clone)]
+pub struct LoadedFeed {
+    pub feed: Feed,
+    /// Number of articles, or why the feed failed to load.
+    pub status: Result<usize, FeedError>,
+    /// What could be parsed, which after a failed download is the cached copy.
+    pub parsed: Option<ParsedFeed>,
+}
+
+async fn load_feed(feed: Feed) -> LoadedFeed {
+    let (response, error) = match download_link(&feed.url).await {
+        Ok(response) => (Some(response), None),
+        Err((error, cached)) => (cached, Some(error)),
+    };
+    let parsed = response.as_ref().map(parse_feed);
+    let status = match (error, &parsed) {
+        (Some(error), _) => Err(error),
+        (None, Some(Ok(parsed))) => Ok(parsed.articles.len()),
+        (None, Some(Err(message))) => Err(FeedError::Parse(message.clone())),
+        (None, None) => Err(FeedError::Network("no response".to_string())),
+    };
+
+    let mut parsed = match (status.is_ok(), response, parsed) {
+        // Only a body that parses becomes the last good copy
+        (true, Some(response), Some(Ok(parsed))) => {
+            let _ = write_cache(FEED_CACHE, &feed.url, &response);
+            Some(parsed)
+        }
+        (_, _, Some(Ok(parsed))) => Some(parsed),
+        // e.g. a captive portal's login page, fall back to the last good copy
+        _ => read_cache::<FeedResponse>(FEED_CACHE, &feed.url).and_then(|x| parse_feed(&x).ok()),
+    };
+    for a in parsed.iter_mut().flat_map(|x| x.articles.iter_mut()) {
+        if let Some(name) = &feed.name {
+            a.publisher = name.clone();
+        }
+        a.feed = feed.url.clone();
+    }
+    LoadedFeed { feed, status, parsed }
 }
 
-pub async fn download_feeds(feeds: Vec<Feed>) -> Vec<(Feed, String)> {
-    let tasks: Vec<tokio::task::JoinHandle<(Feed, Option<String>)>> = feeds
+/// Downloads and parses every feed concurrently, tagging articles with the feed they came from.
+pub async fn load_feeds(feeds: Vec<Feed>) -> Vec<LoadedFeed> {
+    let tasks: Vec<(Feed, tokio::task::JoinHandle<LoadedFeed>)> = feeds
         .into_iter()
-        .map(|f| {
-            tokio::spawn(async move {
-                let url = f.url.clone();
-                let content = download_link(&url).await;
-                (f, content)
-            })
-        })
+        .map(|f| (f.clone(), tokio::spawn(load_feed(f))))
         .collect();
 
-    future::join_all(tasks)
-        .await
-        .into_iter()
-        .filter_map(|x| {
-            let Ok(x) = x else {
-                return None;
-            };
-            let Some(content) = x.1 else {
-                return None;
-            };
-            Some((x.0, content))
-        })
-        .collect()
+    let mut loaded = Vec::new();
+    for (feed, task) in tasks {
+        loaded.push(task.await.unwrap_or_else(|e| LoadedFeed {
+            feed,
+            status: Err(FeedError::Network(e.to_string())),
+            parsed: None,
+        }));
+    }
+    loaded
+}
+
+/**
+ * Format detection
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+    Json,
+}
+
+/// Name of the root element of an XML document, skipping the prolog, comments and doctype.
+fn xml_root_name(body: &str) -> Option<&str> {
+    let mut rest = body;
+    loop {
+        rest = &rest[rest.find('<')? + 1..];
+        if rest.starts_with("!--") {
+            rest = &rest[rest.find("-->")? + 3..];
+        } else if rest.starts_with('?') || rest.starts_with('!') {
+            rest = &rest[rest.find('>')? + 1..];
+        } else {
+            let end = rest.find(|c: char| c.is_whitespace() || c == '>' || c == '/')?;
+            return Some(&rest[..end]);
+        }
+    }
+}
+
+fn sniff_feed_format(body: &str) -> Option<FeedFormat> {
+    let body = body.trim_start_matches('\u{feff}').trim_start();
+    if body.starts_with('{') {
+        return Some(FeedFormat::Json);
+    }
+    let root = xml_root_name(body)?;
+    // Strip any namespace prefix, e.g. `rdf:RDF` or `atom:feed`
+    match root.rsplit(':').next()? {
+        "rss" | "RDF" => Some(FeedFormat::Rss),
+        "feed" => Some(FeedFormat::Atom),
+        _ => None,
+    }
+}
+
+fn content_type_feed_format(content_type: &str) -> Option<FeedFormat> {
+    let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+    match mime.as_str() {
+        "application/feed+json" | "application/json" => Some(FeedFormat::Json),
+        "application/atom+xml" => Some(FeedFormat::Atom),
+        "application/rss+xml" | "application/rdf+xml" => Some(FeedFormat::Rss),
+        _ => None,
+    }
+}
+
+/// Works out the format of a feed from its body, falling back to the
+/// content type when the body is not recognisable.
+pub fn detect_feed_format(response: &FeedResponse) -> Option<FeedFormat> {
+    sniff_feed_format(&response.body)
+        .or_else(|| response.content_type.as_deref().and_then(content_type_feed_format))
+}
+
+pub fn parse_feed(response: &FeedResponse) -> Result<ParsedFeed, String> {
+    match detect_feed_format(response).ok_or("not an RSS, Atom or JSON feed")? {
+        FeedFormat::Rss => parse_rss_feed(&response.body),
+        FeedFormat::Atom => parse_atom_feed(&response.body),
+        FeedFormat::Json => parse_json_feed(&response.body),
+    }
 }
 
 /**
  * RSS feed parsers
 */
+/// Update time from `atom:updated` or `dcterms:modified`, which the rss crate leaves
+/// in the item's extensions under whatever prefix the feed uses.
+fn rss_updated(rss_item: &rss::Item) -> Option<i64> {
+    rss_item
+        .extensions()
+        .values()
+        .flatten()
+        .filter(|(name, _)| matches!(name.as_str(), "updated" | "modified"))
+        .flat_map(|(_, x)| x)
+        .find_map(|x| x.value().and_then(parse_date))
+}
 
 impl Article {
     fn from_rss_item(feed_name: String, rss_item: rss::Item) -> Self {
+        let link = rss_item.link().unwrap_or("").to_string();
+        let updated = rss_updated(&rss_item);
         Self {
+            id: rss_item.guid().map_or(link.clone(), |x| x.value().to_string()),
             title: rss_item.title().unwrap_or("").to_string(),
-            summary: rss_item.content().unwrap_or("").to_string(),
-            date: parse_rfc_2822_date(rss_item.pub_date().unwrap_or("")),
-            link: rss_item.link().unwrap_or("").to_string(),
-            publisher: feed_name
+            // content:encoded usually has the whole article, the description only a teaser
+            summary: rss_item.content().or(rss_item.description()).unwrap_or("").to_string(),
+            date: rss_item
+                .pub_date()
+                .and_then(parse_date)
+                .or_else(|| rss_item.dublin_core_ext().and_then(|x| x.dates().iter().find_map(|x| parse_date(x))))
+                .or(updated),
+            updated,
+            link,
+            author: rss_item
+                .author()
+                .or_else(|| rss_item.dublin_core_ext().and_then(|x| x.creators().first().map(String::as_str)))
+                .unwrap_or("")
+                .to_string(),
+            categories: rss_item.categories().iter().map(|x| x.name().to_string()).collect(),
+            comments: rss_item.comments().map(str::to_string),
+            enclosures: rss_item
+                .enclosure()
+                .map(|x| Enclosure {
+                    url: x.url().to_string(),
+                    mime_type: x.mime_type().to_string(),
+                    length: x.length().trim().parse().ok().filter(|&x| x > 0),
+                })
+                .into_iter()
+                .collect(),
+            publisher: feed_name,
+            ..Default::default()
         }
     }
 }
 
-pub fn parse_rss_feed(content: &str) -> Option<Vec<Article>> {
-    let channel = Channel::read_from(content.as_bytes()).map_or(None, Some)?;
-    Some(
-        channel
+/// Refresh interval in minutes asked for by `<ttl>` and `sy:updatePeriod`/`sy:updateFrequency`.
+fn rss_ttl(channel: &Channel) -> Option<u64> {
+    let ttl = channel.ttl().and_then(|x| x.trim().parse::<u64>().ok());
+    let update_period = channel.syndication_ext().map(|x| {
+        let period = match x.period() {
+            UpdatePeriod::Hourly => 60,
+            UpdatePeriod::Daily => 60 * 24,
+            UpdatePeriod::Weekly => 60 * 24 * 7,
+            UpdatePeriod::Monthly => 60 * 24 * 30,
+            UpdatePeriod::Yearly => 60 * 24 * 365,
+        };
+        period / u64::from(x.frequency().max(1))
+    });
+    ttl.max(update_period)
+}
+
+pub fn parse_rss_feed(content: &str) -> Result<ParsedFeed, String> {
+    let channel = Channel::read_from(content.as_bytes()).map_err(|e| e.to_string())?;
+    let ttl = rss_ttl(&channel);
+    Ok(ParsedFeed {
+        articles: channel
             .clone()
             .into_items()
             .into_iter()
             .map(|x| Article::from_rss_item(channel.title().to_string(), x))
             .collect(),
-    )
+        ttl,
+        skipped: Vec::new(),
+    })
 }
 
 /**
  * Atom parser
  */
-
 impl Article {
+    /// Fails for entries without any url to open.
     fn from_atom_item(
-        atom_item: atom_syndication::Entry,
+        atom_item: &atom_syndication::Entry,
         atom_feed: &atom_syndication::Feed,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, String> {
+        let links = atom_item.links();
+        let link = links
+            .iter()
+            .find(|x| x.rel() == "alternate")
+            .or_else(|| links.first())
+            .map(|x| x.href().to_string())
+            .filter(|x| !x.trim().is_empty())
+            // Ids are often the article's url
+            .or_else(|| Some(atom_item.id().to_string()).filter(|x| x.starts_with("http://") || x.starts_with("https://")))
+            .ok_or_else(|| format!("entry \"{}\" has no link", atom_item.title().value))?;
+        // A missing or unreadable date is read as the epoch
+        let timestamp = |x: &atom_syndication::FixedDateTime| Some(x.timestamp()).filter(|&x| x != 0);
+        let updated = timestamp(atom_item.updated());
+        Ok(Self {
+            id: atom_item.id().to_string(),
             title: atom_item.title().value.clone(),
             summary: atom_item
-                .summary()
-                .map_or("".to_string(), |x| x.value.clone()),
-            link: atom_item.links().first().unwrap().href.clone(),
-            date: atom_item.published().unwrap().timestamp(),
+                .content()
+                .and_then(|x| x.value.clone())
+                .or_else(|| atom_item.summary().map(|x| x.value.clone()))
+                .unwrap_or_default(),
+            link,
+            date: atom_item.published().and_then(timestamp).or(updated),
+            author: atom_item.authors().iter().map(|x| x.name.as_str()).collect::<Vec<_>>().join(", "),
+            categories: atom_item
+                .categories()
+                .iter()
+                .map(|x| x.label().unwrap_or(x.term()).to_string())
+                .collect(),
+            comments: links.iter().find(|x| x.rel() == "replies").map(|x| x.href().to_string()),
+            enclosures: links
+                .iter()
+                .filter(|x| x.rel() == "enclosure")
+                .map(|x| Enclosure {
+                    url: x.href().to_string(),
+                    mime_type: x.mime_type().unwrap_or_default().to_string(),
+                    length: x.length().and_then(|x| x.trim().parse().ok()),
+                })
+                .collect(),
+            updated,
             publisher: atom_feed.title().value.clone(),
-        }
+            ..Default::default()
+        })
     }
 }
 
-pub fn parse_atom_feed(content: &str) -> Option<Vec<Article>> {
-    let Ok(feed) = content.parse::<atom_syndication::Feed>() else {return None};
+const ATOM_NAMESPACE: &str = "http://www.w3.org/2005/Atom";
 
-    Some(
-        feed.entries()
-            .iter()
-            .map(|item| Article::from_atom_item(item.clone(), &feed))
-            .collect(),
-    )
+fn is_atom_date(node: &roxmltree::Node) -> bool {
+    matches!(node.tag_name().name(), "published" | "updated")
+        && matches!(node.tag_name().namespace(), None | Some(ATOM_NAMESPACE))
 }
 
-
-/**
- * Article Downloader 
-*/
-
-
-pub fn download_article_detail(article: &Article) -> Result<String, std::io::Error> {
-   let res = Command::new("curl").arg(&article.link).output()?;
-   let out = String::from_utf8_lossy(&res.stdout);
-   Ok(out.to_string())
-}
-
-pub fn parse_article_detail(detail: &str, width: usize) -> Option<String> {
-
-    let Ok(mut process) = Command::new("lynx")
-        .arg("-stdin")
-        .arg("-dump")
-        .arg(format!("-width={width}"))
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped()) 
-        .spawn() else {return None};
-        
-
-
-    // Write the HTML content to lynx's stdin
-    if let Some(mut stdin) = process.stdin.take() {
-        if stdin.write(detail.as_bytes()).is_err() { return None };
+/// Rewrites the `<published>` and `<updated>` dates of an Atom document as RFC 3339,
+/// so they are read with [`parse_date`] instead of the Atom library's stricter parser.
+/// Dates neither can read are removed, leaving the entry without a date.
+/// `None` when the document is not XML roxmltree can read.
+fn normalize_atom_dates(content: &str) -> Option<String> {
+    let document = roxmltree::Document::parse(content).ok()?;
+    let mut normalized = String::with_capacity(content.len());
+    let mut end = 0;
+    for node in document.descendants().filter(is_atom_date) {
+        let (Some(first), Some(last)) = (node.first_child(), node.last_child()) else {
+            continue;
+        };
+        let text: String = node.children().filter_map(|x| x.text()).collect();
+        // The library fails the whole feed on a date it can't read, so those are left empty
+        let date = parse_date(&text).and_then(|x| chrono::DateTime::from_timestamp(x, 0));
+        normalized.push_str(&content[end..first.range().start]);
+        normalized.push_str(&date.map(|x| x.to_rfc3339()).unwrap_or_default());
+        end = last.range().end;
     }
+    normalized.push_str(&content[end..]);
+    Some(normalized)
+}
 
-    // Capture and print the output from lynx's stdout
-    let Ok(output) = process.wait_with_output() else {return None};
-    if output.status.success() {
-        let response = String::from_utf8_lossy(&output.stdout);
-        Some(response.to_string())
-    } else {
-        println!("Lynx command failed with error:\n{}", 
-        String::from_utf8_lossy(&output.stderr));
-        None
+pub fn parse_atom_feed(content: &str) -> Result<ParsedFeed, String> {
+    let normalized = normalize_atom_dates(content);
+    let feed = normalized
+        .as_deref()
+        .unwrap_or(content)
+        .parse::<atom_syndication::Feed>()
+        .map_err(|e| e.to_string())?;
+
+    let mut articles = Vec::new();
+    let mut skipped = Vec::new();
+    for entry in feed.entries() {
+        match Article::from_atom_item(entry, &feed) {
+            Ok(article) => articles.push(article),
+            Err(reason) => skipped.push(reason),
+        }
     }
+    Ok(ParsedFeed { articles, ttl: None, skipped })
 }
 
 
+/**
+ * JSON Feed parser
+*/
+#[derive(Debug, Deserialize)]
+struct JsonFeedAuthor {
+    name: Option<String>,
+}
 
+#[derive(Debug, Deserialize)]
+struct JsonFeedItem {
+    /// Should be a string, but numbers are common in the wild
+    id: Option<serde_json::Value>,
+    url: Option<String>,
+    title: Option<String>,
+    content_html: Option<String>,
+    content_text: Option<String>,
+    summary: Option<String>,
+    date_published: Option<String>,
+    date_modified: Option<String>,
+    #[serde(default)]
+    authors: Vec<JsonFeedAuthor>,
+    /// JSON Feed 1.0 only allowed a single author
+    author: Option<JsonFeedAuthor>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    attachments: Vec<JsonFeedAttachment>,
+}
 
+#[derive(Debug, Deserialize)]
+struct JsonFeedAttachment {
+    url: String,
+    mime_type: Option<String>,
+    size_in_bytes: Option<u64>,
+}
 
+#[derive(Debug, Deserialize)]
+struct JsonFeed {
+    title: String,
+    #[serde(default)]
+    items: Vec<JsonFeedItem>,
+}
 
+impl Article {
+    fn from_json_item(feed_name: String, json_item: JsonFeedItem) -> Self {
+        let link = json_item.url.unwrap_or_default();
+        Self {
+            id: match json_item.id {
+                Some(serde_json::Value::String(id)) => id,
+                Some(id) => id.to_string(),
+                None => link.clone(),
+            },
+            title: json_item.title.unwrap_or_default(),
+            summary: json_item
+                .content_html
+                .or(json_item.content_text)
+                .or(json_item.summary)
+                .unwrap_or_default(),
+            link,
+            date: json_item
+                .date_published
+                .and_then(|x| parse_date(&x))
+                .or_else(|| json_item.date_modified.as_deref().and_then(parse_date)),
+            updated: json_item.date_modified.and_then(|x| parse_date(&x)),
+            author: json_item
+                .authors
+                .into_iter()
+                .chain(json_item.author)
+                .filter_map(|x| x.name)
+                .collect::<Vec<_>>()
+                .join(", "),
+            categories: json_item.tags,
+            enclosures: json_item
+                .attachments
+                .into_iter()
+                .map(|x| Enclosure { url: x.url, mime_type: x.mime_type.unwrap_or_default(), length: x.size_in_bytes })
+                .collect(),
+            publisher: feed_name,
+            ..Default::default()
+        }
+    }
+}
 
+pub fn parse_json_feed(content: &str) -> Result<ParsedFeed, String> {
+    let feed: JsonFeed = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    Ok(ParsedFeed {
+        articles: feed.items
+            .into_iter()
+            .map(|x| Article::from_json_item(feed.title.clone(), x))
+            .collect(),
+        ttl: None,
+        skipped: Vec::new(),
+    })
+}
 
+/**
+ * Article Downloader 
+*/
+/// Downloads the article page, keeping a copy in the reading cache.
+pub async fn download_article(article: &Article) -> Option<String> {
+    let html = download_page(&article.link).await?;
+    let _ = write_cache(ARTICLE_CACHE, &article.key(), &html);
+    Some(html)
+}
 
+/// Fills the reading cache with `articles`, a few downloads at a time.
+pub async fn prefetch_articles(articles: Vec<Article>, max_bytes: u64, max_age: Duration) {
+    let _ = tokio::task::spawn_blocking(move || evict_cache(ARTICLE_CACHE, max_bytes, max_age)).await;
+    futures::stream::iter(articles)
+        .for_each_concurrent(4, |article| async move {
+            download_article(&article).await;
+        })
+        .await;
+}
 
+/// Downloads the html of any page, e.g. a link followed from an article.
+pub async fn download_page(url: &str) -> Option<String> {
+    let response = client().get(url).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response.text().await.ok()
+}
 
+/// Downloads and decodes an image shown in an article, scaled down if it is huge.
+pub async fn download_image(url: &str) -> Option<image::DynamicImage> {
+    const MAX_BYTES: usize = 20 * 1024 * 1024;
+    let response = client().get(url).send().await.ok()?;
+    if !response.status().is_success() || response.content_length().is_some_and(|x| x as usize > MAX_BYTES) {
+        return None;
+    }
+    let bytes = response.bytes().await.ok()?;
+    if bytes.len() > MAX_BYTES {
+        return None;
+    }
+    tokio::task::spawn_blocking(move || {
+        let image = image::load_from_memory(&bytes).ok()?;
+        Some(if image.width() > 2048 || image.height() > 2048 { image.thumbnail(2048, 2048) } else { image })
+    })
+    .await
+    .ok()
+    .flatten()
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    fn atom(entries: &str) -> String {
+        format!("<?xml version=\"1.0\"?><feed xmlns=\"http://www.w3.org/2005/Atom\"><title>Atom</title><id>urn:feed</id><updated>2026-10-17T00:00:00Z</updated>{entries}</feed>")
+    }
 
+    fn response(body: &str, content_type: Option<&str>) -> FeedResponse {
+        FeedResponse { content_type: content_type.map(str::to_string), body: body.to_string(), etag: None, last_modified: None }
+    }
 
+    #[test]
+    fn detects_feed_formats() {
+        let formats = [
+            ("<?xml version=\"1.0\"?><rss version=\"2.0\"><channel/></rss>", None, Some(FeedFormat::Rss)),
+            ("\u{feff}<?xml version=\"1.0\"?>\n<rss><channel/></rss>", None, Some(FeedFormat::Rss)),
+            (
+                "<?xml version=\"1.0\"?>\n<!-- generated <feed> -->\n<!DOCTYPE rss SYSTEM \"rss.dtd\">\n<?xml-stylesheet href=\"a.xsl\"?>\n<rss/>",
+                None,
+                Some(FeedFormat::Rss),
+            ),
+            ("<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\"></rdf:RDF>", None, Some(FeedFormat::Rss)),
+            ("<feed xmlns=\"http://www.w3.org/2005/Atom\"></feed>", None, Some(FeedFormat::Atom)),
+            ("<atom:feed xmlns:atom=\"http://www.w3.org/2005/Atom\"></atom:feed>", None, Some(FeedFormat::Atom)),
+            ("  {\"version\": \"https://jsonfeed.org/version/1.1\", \"title\": \"J\", \"items\": []}", None, Some(FeedFormat::Json)),
+            // The body wins over a wrong content type
+            ("<feed></feed>", Some("application/rss+xml"), Some(FeedFormat::Atom)),
+            // Unrecognisable bodies fall back to the content type
+            ("garbage", Some("application/atom+xml; charset=utf-8"), Some(FeedFormat::Atom)),
+            ("garbage", Some("Application/Feed+JSON"), Some(FeedFormat::Json)),
+            ("garbage", Some("text/plain"), None),
+            ("<!DOCTYPE html><html><body>Sign in to the WiFi</body></html>", Some("text/html"), None),
+            ("", None, None),
+        ];
+        for (body, content_type, expected) in formats {
+            assert_eq!(detect_feed_format(&response(body, content_type)), expected, "{body}");
+        }
+    }
 
+    #[test]
+    fn rejects_html_error_pages() {
+        let page = "<!DOCTYPE html>\n<html><head><title>502</title></head><body>Bad gateway</body></html>";
+        assert!(parse_feed(&response(page, Some("text/html"))).is_err());
+        // Even when the server claims it is a feed
+        assert!(parse_feed(&response(page, Some("application/rss+xml"))).is_err());
+    }
 
+    #[test]
+    fn rss_items_read_update_times_from_extensions() {
+        let body = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:dcterms="http://purl.org/dc/terms/"><channel><title>R</title>
+  <item><title>Atom</title><guid>1</guid><pubDate>Sat, 17 Oct 2026 08:00:00 GMT</pubDate><atom:updated>2026-10-17T10:00:00Z</atom:updated></item>
+  <item><title>Dcterms</title><guid>2</guid><dcterms:modified>2026-10-17T10:00:00Z</dcterms:modified></item>
+  <item><title>Neither</title><guid>3</guid><pubDate>Sat, 17 Oct 2026 08:00:00 GMT</pubDate></item>
+</channel></rss>"#;
+        let feed = parse_rss_feed(body).unwrap();
+        let dates: Vec<(Option<i64>, Option<i64>)> = feed.articles.iter().map(|x| (x.date, x.updated)).collect();
+        assert_eq!(
+            dates,
+            [
+                (Some(1792224000), Some(1792231200)),
+                (Some(1792231200), Some(1792231200)),
+                (Some(1792224000), None),
+            ]
+        );
+    }
 
+    #[test]
+    fn parses_json_feeds() {
+        let body = r#"{"version": "https://jsonfeed.org/version/1", "title": "J", "items": [
+            {"id": 2, "url": "https://j.example/2", "title": "Two", "content_text": "Text", "date_published": "2026-10-17T08:00:00Z", "author": {"name": "Ann"}},
+            {"id": "three", "content_html": "<p>Html</p>", "summary": "Summary", "date_modified": "2026-10-17T08:00:00Z"}
+        ]}"#;
+        let feed = parse_feed(&response(body, None)).unwrap();
+        let articles: Vec<(&str, &str, &str, Option<i64>, &str)> = feed
+            .articles
+            .iter()
+            .map(|x| (x.id.as_str(), x.link.as_str(), x.summary.as_str(), x.date, x.author.as_str()))
+            .collect();
+        assert_eq!(
+            articles,
+            [
+                ("2", "https://j.example/2", "Text", Some(1792224000), "Ann"),
+                ("three", "", "<p>Html</p>", Some(1792224000), ""),
+            ]
+        );
+    }
 
+    #[test]
+    fn atom_entries_fall_back_for_links_and_dates() {
+        let feed = parse_atom_feed(&atom(
+            "<entry><title>Alternate</title><id>1</id><link rel=\"enclosure\" href=\"https://a.example/ep.mp3\"/><link href=\"https://a.example/1\"/><published>2026-10-16T08:00:00Z</published><updated>2026-10-16T10:00:00Z</updated></entry>\
+             <entry><title>Related</title><id>2</id><link rel=\"related\" href=\"https://a.example/2\"/><updated>2026-10-16T10:00:00Z</updated></entry>\
+             <entry><title>Id</title><id>https://a.example/3</id></entry>\
+             <entry><title>Nothing</title><id>urn:4</id></entry>",
+        ))
+        .unwrap();
+        let links: Vec<&str> = feed.articles.iter().map(|x| x.link.as_str()).collect();
+        assert_eq!(links, ["https://a.example/1", "https://a.example/2", "https://a.example/3"]);
+        let dates: Vec<Option<i64>> = feed.articles.iter().map(|x| x.date).collect();
+        assert_eq!(dates, [Some(1792137600), Some(1792144800), None]);
+        assert_eq!(feed.skipped, ["entry \"Nothing\" has no link"]);
+    }
 
+    #[test]
+    fn atom_content_is_preferred_over_summary() {
+        let feed = parse_atom_feed(&atom(
+            "<entry><title>A</title><id>1</id><link href=\"https://a.example/1\"/><summary>Teaser</summary><content type=\"html\">&lt;p&gt;Whole&lt;/p&gt;</content></entry>\
+             <entry><title>B</title><id>2</id><link href=\"https://a.example/2\"/><summary>Teaser</summary></entry>",
+        ))
+        .unwrap();
+        let summaries: Vec<&str> = feed.articles.iter().map(|x| x.summary.as_str()).collect();
+        assert_eq!(summaries, ["<p>Whole</p>", "Teaser"]);
+    }
 
+    #[test]
+    fn atom_dates_are_read_leniently() {
+        let feed = parse_atom_feed(&atom(
+            "<entry><title>A</title><id>1</id><link href=\"https://a.example/1\"/><published>Saturday, 17 Oct 2026 08:00 CEST</published><updated> Sat, 17 Oct 2026 09:00:00 +0200 </updated></entry>\
+             <entry><title>B</title><id>2</id><link href=\"https://a.example/2\"/><published><![CDATA[2026-10-17 08:00:00Z]]></published></entry>",
+        ))
+        .unwrap();
+        let dates: Vec<(Option<i64>, Option<i64>)> = feed.articles.iter().map(|x| (x.date, x.updated)).collect();
+        assert_eq!(dates, [(Some(1792216800), Some(1792220400)), (Some(1792224000), None)]);
+    }
 
-
-
-
-
-
-
-
+    #[test]
+    fn unreadable_atom_dates_only_lose_the_date() {
+        let feed = parse_atom_feed(&atom(
+            "<entry><title>Good</title><id>1</id><link href=\"https://a.example/1\"/><published>2026-10-16T08:00:00Z</published></entry>\
+             <entry><title>Bad</title><id>2</id><link href=\"https://a.example/2\"/><published>last Tuesday-ish</published><updated>soon</updated></entry>",
+        ))
+        .unwrap();
+        let articles: Vec<(&str, Option<i64>)> = feed.articles.iter().map(|x| (x.title.as_str(), x.date)).collect();
+        assert_eq!(articles, [("Good", Some(1792137600)), ("Bad", None)]);
+        assert!(feed.skipped.is_empty());
+    }
+}
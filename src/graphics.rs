@@ -0,0 +1,335 @@
+//! Inline images for the detail view.
+//!
+//! Terminals that understand the kitty graphics protocol, iTerm2 inline images
+//! or sixel get the image itself, drawn with escape sequences after ratatui has
+//! drawn the frame. Everything else gets coloured half blocks.
+
+use std::{collections::{HashMap, HashSet}, io::{self, Write}};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use crossterm::{cursor::MoveTo, style::Print};
+use image::{imageops::FilterType, DynamicImage, ImageFormat, RgbaImage};
+use ratatui::{buffer::Buffer, layout::Rect, style::Color};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Kitty,
+    Iterm2,
+    Sixel,
+    Halfblocks,
+}
+
+/// Guesses the best protocol from the environment the terminal sets up.
+pub fn detect_protocol() -> Protocol {
+    let var = |name: &str| std::env::var(name).unwrap_or_default().to_lowercase();
+    let term = var("TERM");
+    let program = var("TERM_PROGRAM");
+    // Multiplexers pass the variables of the outer terminal but not its escape sequences
+    if term.starts_with("screen") || term.starts_with("tmux") || !var("TMUX").is_empty() {
+        return Protocol::Halfblocks;
+    }
+    if term.contains("kitty") || term.contains("ghostty") || !var("KITTY_WINDOW_ID").is_empty() || program == "ghostty" {
+        Protocol::Kitty
+    } else if program == "iterm.app" || program == "wezterm" || !var("WEZTERM_EXECUTABLE").is_empty() {
+        Protocol::Iterm2
+    } else if ["foot", "mlterm", "contour", "sixel"].iter().any(|x| term.contains(x)) || !var("KONSOLE_VERSION").is_empty() {
+        Protocol::Sixel
+    } else {
+        Protocol::Halfblocks
+    }
+}
+
+/// Size of a terminal cell in pixels, with a common guess when the terminal doesn't say.
+fn cell_size() -> (u32, u32) {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+            u32::from(size.width / size.columns).max(1),
+            u32::from(size.height / size.rows).max(1),
+        ),
+        _ => (8, 16),
+    }
+}
+
+/// Largest size within `bounds` with the aspect ratio of `size`, never scaling up.
+fn fit(size: (u32, u32), bounds: (u32, u32)) -> (u32, u32) {
+    let scale = (bounds.0 as f64 / size.0 as f64).min(bounds.1 as f64 / size.1 as f64).min(1.0);
+    (
+        ((size.0 as f64 * scale) as u32).max(1),
+        ((size.1 as f64 * scale) as u32).max(1),
+    )
+}
+
+/// An image drawn with escape sequences, in cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    url: String,
+    area: Rect,
+    /// Size of the area the image was fitted to.
+    bounds: (u16, u16),
+}
+
+impl Placement {
+    fn key(&self) -> (String, u16, u16) {
+        (self.url.clone(), self.bounds.0, self.bounds.1)
+    }
+}
+
+#[derive(Debug)]
+pub struct Graphics {
+    pub protocol: Protocol,
+    /// Pixels per cell.
+    cell: (u32, u32),
+    /// Downloaded images by url, `None` while loading or if they could not be loaded.
+    pub images: HashMap<String, Option<DynamicImage>>,
+    /// Images to draw after the current frame, collected by `ui::render`.
+    pub placements: Vec<Placement>,
+    /// Images on screen, emptied when the terminal has been wiped.
+    pub drawn: Vec<Placement>,
+    /// Images scaled to fit an area, by url and area size.
+    fitted: HashMap<(String, u16, u16), RgbaImage>,
+    escapes: HashMap<(String, u16, u16), String>,
+    /// Kitty image ids, by url and area size.
+    kitty_ids: HashMap<(String, u16, u16), u32>,
+    last_kitty_id: u32,
+    /// Kitty images the terminal has been sent since it was last wiped.
+    transmitted: HashSet<u32>,
+    /// Kitty images of a closed article, deleted from the terminal on the next write.
+    stale: Vec<u32>,
+}
+
+impl Graphics {
+    pub fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            cell: cell_size(),
+            images: HashMap::new(),
+            placements: Vec::new(),
+            drawn: Vec::new(),
+            fitted: HashMap::new(),
+            escapes: HashMap::new(),
+            kitty_ids: HashMap::new(),
+            last_kitty_id: 0,
+            transmitted: HashSet::new(),
+            stale: Vec::new(),
+        }
+    }
+
+    /// Forgets every image, e.g. when another article is opened.
+    pub fn clear(&mut self) {
+        self.images.clear();
+        self.fitted.clear();
+        self.escapes.clear();
+        self.stale.extend(self.kitty_ids.drain().map(|(_, id)| id));
+        self.transmitted.clear();
+    }
+
+    /// The terminal has been wiped, taking the images on it with it.
+    pub fn wiped(&mut self) {
+        self.drawn.clear();
+        self.transmitted.clear();
+    }
+
+    /// Rows of the images on screen. Their pixels are not part of ratatui's buffer, so
+    /// these rows have to be repainted before the images move. Kitty draws images on a
+    /// layer of their own, which leaves nothing to repaint.
+    pub fn drawn_rows(&self) -> Vec<u16> {
+        if self.protocol == Protocol::Kitty {
+            return Vec::new();
+        }
+        let mut rows: Vec<u16> = self.drawn.iter().flat_map(|x| x.area.top()..x.area.bottom()).collect();
+        rows.sort_unstable();
+        rows.dedup();
+        rows
+    }
+
+    /// The image at `url` scaled for `area`, or `None` if it isn't loaded.
+    fn fitted(&mut self, url: &str, area: Rect) -> Option<&RgbaImage> {
+        let key = (url.to_string(), area.width, area.height);
+        if !self.fitted.contains_key(&key) {
+            let image = self.images.get(url)?.as_ref()?;
+            let (cell_width, cell_height) = self.cell;
+            let (width, height) = fit(
+                (image.width(), image.height()),
+                (u32::from(area.width) * cell_width, u32::from(area.height) * cell_height),
+            );
+            let (width, height) = match self.protocol {
+                // Every cell shows two pixels, one above the other
+                Protocol::Halfblocks => (width.div_ceil(cell_width), (height * 2).div_ceil(cell_height)),
+                _ => (width, height),
+            };
+            let fitted = image.resize_exact(width, height, FilterType::Triangle).to_rgba8();
+            self.fitted.insert(key.clone(), fitted);
+        }
+        self.fitted.get(&key)
+    }
+
+    /// Draws the image at `url` into `area`: right away as half blocks, or after the
+    /// frame for the other protocols.
+    pub fn render(&mut self, url: &str, area: Rect, buf: &mut Buffer) {
+        let protocol = self.protocol;
+        let (cell_width, cell_height) = self.cell;
+        let Some(image) = self.fitted(url, area) else { return };
+        if protocol == Protocol::Halfblocks {
+            render_halfblocks(image, area, buf);
+            return;
+        }
+        let placement = Placement {
+            url: url.to_string(),
+            area: Rect {
+                width: image.width().div_ceil(cell_width) as u16,
+                height: image.height().div_ceil(cell_height) as u16,
+                ..area
+            },
+            bounds: (area.width, area.height),
+        };
+        self.placements.push(placement);
+    }
+
+    /// Writes the images placed by the last `render` calls to the terminal.
+    pub fn write(&mut self, out: &mut impl Write) -> io::Result<()> {
+        if self.protocol == Protocol::Kitty {
+            // Kitty keeps images until they are deleted. Only the placements on screen
+            // go, the images stay in the terminal to be placed again without resending.
+            for id in self.stale.drain(..) {
+                crossterm::queue!(out, Print(format!("\x1b_Ga=d,d=I,i={id},q=2\x1b\\")))?;
+            }
+            for placement in &self.drawn {
+                if let Some(id) = self.kitty_ids.get(&placement.key()) {
+                    crossterm::queue!(out, Print(format!("\x1b_Ga=d,d=i,i={id},q=2\x1b\\")))?;
+                }
+            }
+        }
+        for placement in self.placements.clone() {
+            let key = placement.key();
+            if !self.escapes.contains_key(&key) {
+                let Some(image) = self.fitted.get(&key) else { continue };
+                let escape = match self.protocol {
+                    Protocol::Kitty => {
+                        self.last_kitty_id += 1;
+                        self.kitty_ids.insert(key.clone(), self.last_kitty_id);
+                        kitty(image, self.last_kitty_id)
+                    }
+                    Protocol::Iterm2 => iterm2(image),
+                    Protocol::Sixel => sixel(image),
+                    Protocol::Halfblocks => continue,
+                };
+                self.escapes.insert(key.clone(), escape);
+            }
+            crossterm::queue!(out, MoveTo(placement.area.x, placement.area.y))?;
+            match self.kitty_ids.get(&key) {
+                Some(&id) => {
+                    if self.transmitted.insert(id) {
+                        crossterm::queue!(out, Print(&self.escapes[&key]))?;
+                    }
+                    crossterm::queue!(out, Print(format!("\x1b_Ga=p,i={id},q=2,C=1\x1b\\")))?;
+                }
+                None => crossterm::queue!(out, Print(&self.escapes[&key]))?,
+            }
+        }
+        out.flush()?;
+        self.drawn = self.placements.clone();
+        Ok(())
+    }
+}
+
+fn render_halfblocks(image: &RgbaImage, area: Rect, buf: &mut Buffer) {
+    let color = |x: u32, y: u32| match image.get_pixel_checked(x, y) {
+        Some(pixel) if pixel[3] >= 128 => Color::Rgb(pixel[0], pixel[1], pixel[2]),
+        _ => Color::Reset,
+    };
+    for row in 0..image.height().div_ceil(2).min(u32::from(area.height)) {
+        for col in 0..image.width().min(u32::from(area.width)) {
+            if let Some(cell) = buf.cell_mut((area.x + col as u16, area.y + row as u16)) {
+                cell.set_symbol("▀").set_fg(color(col, row * 2)).set_bg(color(col, row * 2 + 1));
+            }
+        }
+    }
+}
+
+fn png(image: &RgbaImage) -> Vec<u8> {
+    let mut data = io::Cursor::new(Vec::new());
+    let _ = image.write_to(&mut data, ImageFormat::Png);
+    data.into_inner()
+}
+
+/// Kitty graphics protocol, sending a PNG in chunks of at most 4096 bytes to be
+/// placed later by `id`.
+fn kitty(image: &RgbaImage, id: u32) -> String {
+    let data = STANDARD.encode(png(image));
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    let mut escape = String::new();
+    for (idx, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(idx + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if idx == 0 {
+            escape.push_str(&format!("\x1b_Ga=t,f=100,i={id},q=2,m={more};{chunk}\x1b\\"));
+        } else {
+            escape.push_str(&format!("\x1b_Gm={more};{chunk}\x1b\\"));
+        }
+    }
+    escape
+}
+
+/// iTerm2 inline image.
+fn iterm2(image: &RgbaImage) -> String {
+    let data = png(image);
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={}px;height={}px;preserveAspectRatio=1;doNotMoveCursor=1:{}\x07",
+        data.len(),
+        image.width(),
+        image.height(),
+        STANDARD.encode(&data),
+    )
+}
+
+/// Sixel image using the 216 colour cube, leaving transparent pixels alone.
+fn sixel(image: &RgbaImage) -> String {
+    let level = |x: u8| (u32::from(x) * 5 + 127) / 255;
+    let (width, height) = image.dimensions();
+    let indices: Vec<Option<u32>> = image
+        .pixels()
+        .map(|pixel| (pixel[3] >= 128).then(|| level(pixel[0]) * 36 + level(pixel[1]) * 6 + level(pixel[2])))
+        .collect();
+    let index = |x: u32, y: u32| indices[(y * width + x) as usize];
+    let mut escape = format!("\x1bP0;1;0q\"1;1;{width};{height}");
+    for color in 0..216 {
+        let percent = |x: u32| x * 100 / 5;
+        escape.push_str(&format!("#{color};2;{};{};{}", percent(color / 36), percent(color / 6 % 6), percent(color % 6)));
+    }
+    for band in (0..height).step_by(6) {
+        let rows = band..(band + 6).min(height);
+        let mut colors: Vec<u32> = rows.clone().flat_map(|y| (0..width).filter_map(move |x| index(x, y))).collect();
+        colors.sort_unstable();
+        colors.dedup();
+        for color in colors {
+            escape.push_str(&format!("#{color}"));
+            let sixels = (0..width).map(|x| {
+                let bits = rows.clone().filter(|&y| index(x, y) == Some(color)).fold(0, |bits, y| bits | 1 << (y - band));
+                char::from(63 + bits as u8)
+            });
+            // Run length encoding of repeated sixels
+            let mut run: Option<(char, usize)> = None;
+            for c in sixels.chain(std::iter::once('\0')) {
+                match &mut run {
+                    Some((last, count)) if *last == c => *count += 1,
+                    _ => {
+                        if let Some((last, count)) = run {
+                            if count > 3 {
+                                escape.push_str(&format!("!{count}{last}"));
+                            } else {
+                                escape.extend(std::iter::repeat_n(last, count));
+                            }
+                        }
+                        run = Some((c, 1));
+                    }
+                }
+            }
+            escape.push('$');
+        }
+        escape.push('-');
+    }
+    escape.push_str("\x1b\\");
+    escape
+}
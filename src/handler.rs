@@ -1,56 +1,305 @@
-use std::{path::PathBuf, process::Command};
+use std::time::Duration;
 use chrono::Utc;
-use dirs::home_dir;
-use crate::{app::{App, AppDetail, AppResult, AppState}, feedloader::{Feed, download_feeds, download_article_detail, parse_article_detail, parse_rss_feed, parse_atom_feed}, article::Article};
+use crate::{browser::browser_command, search::Search, cache::{has_cache, read_cache}, feedloader::ARTICLE_CACHE, app::{App, AppDetail, AppResult, AppState, DetailPage, FeedReport}, event::NetworkEvent, article::Article, render::{render_article, render_feed_content}, store::{merge_articles, prune_articles, read_saved}};
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::text::Text;
 
-fn clean(string: &str) -> String {
-    string.chars()
-        .filter(|c| c.is_alphanumeric())
-        .flat_map(|c| c.to_lowercase())
-        .collect()
+/// Width available to the article inside the detail view's borders.
+fn detail_width(app: &App) -> usize {
+    app.area.width.saturating_sub(app.sidebar_width() + 2)
 }
 
-fn find_line_with_substring(big_string: &str, substring: &str) -> usize {
-    // Iterate through each line
-    let clean_sub: String = clean(substring);
-    for (idx, line) in big_string.lines().enumerate() {
-        if clean(line).contains(&clean_sub) {
-            return idx + 1; 
+/// Rows available to the article inside the detail view's borders, roughly.
+fn detail_height(app: &App) -> usize {
+    (app.area.height.saturating_sub(1) * 4 / 5).saturating_sub(2)
+}
+
+/// Moves `direction` pages, or `1/fraction` of one, through the headlines or the open article.
+fn scroll_page(app: &mut App, direction: i64, fraction: usize) {
+    let page = detail_height(app);
+    match &mut app.mode {
+        AppState::Normal => {
+            let step = (app.headline_height / fraction).max(1) as i64;
+            app.move_selection(direction * step);
+        }
+        AppState::Detail(dtl) => {
+            let step = (page / fraction).max(1) as i64;
+            dtl.scroll_index = (dtl.scroll_index as i64 + direction * step).clamp(0, dtl.content.lines.len() as i64) as usize;
         }
+        _ => {}
+    }
+}
+
+/// Renders the page's html, or a placeholder while it loads or if the download failed.
+fn render_detail_content(dtl: &mut AppDetail, width: usize, images: bool) {
+    dtl.links.clear();
+    dtl.link_lines.clear();
+    dtl.images.clear();
+    dtl.selected_link = None;
+    dtl.link_input = None;
+    dtl.content = if dtl.loading {
+        Text::from("Loading article...")
+    } else if dtl.html.is_empty() {
+        Text::from("Could not download article")
+    } else {
+        let rendered = if dtl.embedded && dtl.back.is_empty() {
+            render_feed_content(&dtl.html, &dtl.url, width, images)
+        } else {
+            render_article(&dtl.html, &dtl.url, width, images)
+        };
+        dtl.links = rendered.links;
+        dtl.images = rendered.images;
+        dtl.link_lines = rendered.link_lines;
+        rendered.text
+    };
+    dtl.scroll_index = 0;
+    dtl.update_hits();
+}
+
+/// Highlights link `number` and scrolls to it.
+fn select_link(dtl: &mut AppDetail, number: usize) {
+    if number == 0 || number > dtl.links.len() {
+        dtl.selected_link = None;
+        return;
+    }
+    dtl.selected_link = Some(number);
+    dtl.scroll_index = dtl.link_lines[number - 1].saturating_sub(2);
+}
+
+/// Opens the selected link inside the detail view, keeping the current page to go back to.
+fn follow_link(app: &mut App) {
+    let AppState::Detail(dtl) = &mut app.mode else { return };
+    let Some(url) = dtl.selected_link.and_then(|x| dtl.links.get(x - 1)).cloned() else { return };
+    dtl.back.push(DetailPage { url: dtl.url.clone(), html: std::mem::take(&mut dtl.html), scroll_index: dtl.scroll_index });
+    dtl.url = url.clone();
+    dtl.loading = true;
+    let (width, images) = (detail_width(app), app.show_images);
+    if let AppState::Detail(dtl) = &mut app.mode {
+        render_detail_content(dtl, width, images);
+    }
+    app.request(NetworkEvent::RequestPage(url));
+}
+
+/// Returns to the page the current one was opened from.
+fn go_back(dtl: &mut AppDetail, width: usize, images: bool) {
+    let Some(page) = dtl.back.pop() else { return };
+    dtl.url = page.url;
+    dtl.html = page.html;
+    dtl.loading = false;
+    render_detail_content(dtl, width, images);
+    dtl.scroll_index = page.scroll_index.min(dtl.content.lines.len());
+}
+
+/// Renders the open page again, keeping the reader's place.
+fn rerender_detail(app: &mut App) {
+    let (width, images) = (detail_width(app), app.show_images);
+    if let AppState::Detail(dtl) = &mut app.mode {
+        let (scroll_index, selected_link) = (dtl.scroll_index, dtl.selected_link);
+        render_detail_content(dtl, width, images);
+        dtl.scroll_index = scroll_index.min(dtl.content.lines.len());
+        dtl.selected_link = selected_link;
+    }
+}
+
+/// Re-wraps the open article for the new terminal width.
+pub fn handle_resize(width: u16, app: &mut App) {
+    app.area.width = width as usize;
+    // The terminal is wiped before the next frame
+    app.graphics.wiped();
+    rerender_detail(app);
+}
+
+/// Asks the network task to download every enabled feed.
+pub fn request_feed_load(app: &mut App) {
+    let feeds = app.config.enabled_feeds();
+    app.load_feeds(feeds);
+}
+
+/// Asks the network task for the article page, or returns the copy saved when it was starred.
+fn request_article_content(app: &mut App, article: &Article) -> Option<String> {
+    if let Some(content) = read_saved(&article.key()).filter(|_| article.saved) {
+        return Some(content);
+    }
+    if let Some(content) = read_cache(ARTICLE_CACHE, &article.key()) {
+        return Some(content);
     }
-    0 
-}
-
-pub async fn download_feed(app: &mut App) {
-    let mut config_path: PathBuf = home_dir().expect("Couldn't find home dir");
-    config_path.push(".config/newsterm/feeds");
-    let feed_file = std::fs::read_to_string(config_path).unwrap();
-    let mut downloaded: Vec<Article> = download_feeds(feed_file.lines().map(|x| Feed { url: x.to_string() } ).collect())
-     .await
-     .into_iter()
-     .map(|(_, content)| {
-         let rss_parse = parse_rss_feed(&content);
-         let parsed_feed = match rss_parse {
-             Some(v) => Some(v), 
-             None => parse_atom_feed(&content)
-         };
-
-         parsed_feed.map(|v| v.into_iter())
-     })
-     .flatten()
-     .flatten()
-     .collect();
-    
-    
-     downloaded.sort_by(|x, y| y.date.cmp(&x.date));
-     app.articles = downloaded;
-     app.last_update_timestamp = Utc::now().timestamp();
+    app.request(NetworkEvent::RequestArticleDetail(article.clone()));
+    None
+}
+
+/// Evicts old pages from the reading cache and downloads the newest unread articles into it.
+fn update_reading_cache(app: &App) {
+    let settings = &app.config.settings;
+    let max_age = Duration::from_secs(settings.article_cache_days * 24 * 60 * 60);
+    let articles: Vec<Article> = app
+        .articles
+        .iter()
+        .filter(|x| !x.read)
+        .take(settings.prefetch)
+        .filter(|x| !x.saved && !x.link.is_empty() && !has_cache(ARTICLE_CACHE, &x.key()))
+        .cloned()
+        .collect();
+    // Not counted as loading, nothing waits for it
+    let _ = app.network.send(NetworkEvent::Prefetch(articles, settings.article_cache_mb * 1024 * 1024, max_age));
+}
 
+/// Handles the results of network requests.
+pub fn handle_network_event(network_event: NetworkEvent, app: &mut App) {
+    match network_event {
+        NetworkEvent::FeedLoaded(feeds) => {
+            app.loading = app.loading.saturating_sub(1);
+            let now = Utc::now().timestamp();
+            let selected = app.selected_article().map(|x| x.key());
+            let mut downloaded = Vec::new();
+            for loaded in feeds {
+                let url = loaded.feed.url;
+                let last_success = match loaded.status {
+                    Ok(_) => Some(now),
+                    Err(_) => app.feed_reports.get(&url).and_then(|x| x.last_success),
+                };
+                let skipped = loaded.parsed.as_ref().map_or(Vec::new(), |x| x.skipped.clone());
+                app.feed_reports.insert(url.clone(), FeedReport { status: loaded.status, last_success, skipped });
+                let Some(parsed) = loaded.parsed else { continue };
+                match parsed.ttl {
+                    Some(ttl) => app.feed_ttl.insert(url, ttl),
+                    None => app.feed_ttl.remove(&url),
+                };
+                downloaded.extend(parsed.articles);
+            }
+            merge_articles(&mut app.articles, downloaded, now);
+            prune_articles(&mut app.articles, &app.config, now);
+            app.articles.sort_by_key(|x| std::cmp::Reverse(x.time()));
+            app.history_changed = true;
+            app.preview_cache = None;
+            // Indices moved around, keep the same article selected
+            app.view.clear();
+            app.update_view();
+            if let Some(key) = selected {
+                app.select_article(&key);
+            }
+            app.last_update_timestamp = now;
+            update_reading_cache(app);
+        }
+        NetworkEvent::RecieveArticleDetail(article, html) => {
+            app.loading = app.loading.saturating_sub(1);
+            // Articles starred before they were opened get their copy once it arrives
+            if let Some(html) = &html {
+                if app.articles.iter().any(|x| x.key() == article.key() && x.starred && !x.saved) {
+                    app.set_starred(&article.key(), true, Some(html.clone()));
+                }
+            }
+            let (width, images) = (detail_width(app), app.show_images);
+            match &mut app.mode {
+                AppState::Detail(dtl) if dtl.loading && dtl.back.is_empty() && dtl.article.key() == article.key() => {
+                    dtl.html = html.unwrap_or_default();
+                    dtl.loading = false;
+                    render_detail_content(dtl, width, images);
+                }
+                _ => {}
+            }
+        }
+        NetworkEvent::RecievePage(url, html) => {
+            app.loading = app.loading.saturating_sub(1);
+            let (width, images) = (detail_width(app), app.show_images);
+            match &mut app.mode {
+                AppState::Detail(dtl) if dtl.loading && dtl.url == url => {
+                    dtl.html = html.unwrap_or_default();
+                    dtl.loading = false;
+                    render_detail_content(dtl, width, images);
+                }
+                _ => {}
+            }
+        }
+        NetworkEvent::RecieveImage(url, image) => {
+            app.loading = app.loading.saturating_sub(1);
+            // Ignore images of articles that have been closed since
+            if let Some(slot) = app.graphics.images.get_mut(&url) {
+                *slot = image;
+            }
+        }
+        NetworkEvent::BrowserFailed(e) => app.show_message(e),
+        NetworkEvent::RequestFeedLoad(_)
+        | NetworkEvent::OpenBrowser(_)
+        | NetworkEvent::RequestArticleDetail(..)
+        | NetworkEvent::RequestPage(_)
+        | NetworkEvent::Prefetch(..)
+        | NetworkEvent::RequestImage(_) => {}
+    }
+}
+
+/// Keys typed into the headline search.
+fn handle_search_key(key_event: KeyEvent, app: &mut App) {
+    let Some(search) = &mut app.search else { return };
+    match key_event.code {
+        // Back to all headlines, matches stay highlighted for n/N
+        KeyCode::Enter => {
+            app.mode = AppState::Normal;
+            if search.pattern.is_none() {
+                app.search = None;
+            }
+        }
+        KeyCode::Esc => {
+            app.mode = AppState::Normal;
+            app.search = None;
+        }
+        KeyCode::Backspace => {
+            search.query.pop();
+        }
+        KeyCode::Char('r') if key_event.modifiers == KeyModifiers::CONTROL => search.regex = !search.regex,
+        KeyCode::Char(c) if !key_event.modifiers.contains(KeyModifiers::CONTROL) => search.query.push(c),
+        _ => return,
+    }
+    if let Some(search) = &mut app.search {
+        search.update();
+    }
+    app.update_view();
+}
+
+/// Keys typed into the search of the open page.
+fn handle_detail_search_key(key_event: KeyEvent, dtl: &mut AppDetail) {
+    let Some(search) = &mut dtl.search else { return };
+    match key_event.code {
+        KeyCode::Enter => {
+            dtl.searching = false;
+            if search.pattern.is_none() {
+                dtl.search = None;
+            }
+        }
+        KeyCode::Esc => {
+            dtl.searching = false;
+            dtl.search = None;
+        }
+        KeyCode::Backspace => {
+            search.query.pop();
+        }
+        KeyCode::Char('r') if key_event.modifiers == KeyModifiers::CONTROL => search.regex = !search.regex,
+        KeyCode::Char(c) if !key_event.modifiers.contains(KeyModifiers::CONTROL) => search.query.push(c),
+        _ => return,
+    }
+    if let Some(search) = &mut dtl.search {
+        search.update();
+    }
+    // Jump to the first match from where the reader is as the search is typed
+    let scroll_index = dtl.scroll_index;
+    dtl.update_hits();
+    if let Some(hit) = dtl.hits.iter().position(|(line, _)| *line >= scroll_index) {
+        dtl.select_hit(hit);
+    }
 }
 
 /// Handles the key events and updates the state of [`App`].
 pub async fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<()> {
+    match &mut app.mode {
+        AppState::Search => {
+            handle_search_key(key_event, app);
+            return Ok(());
+        }
+        AppState::Detail(dtl) if dtl.searching => {
+            handle_detail_search_key(key_event, dtl);
+            return Ok(());
+        }
+        _ => {}
+    }
     match key_event.code {
         // Exit application on `ESC` or `q`
         KeyCode::Char('q')  => {
@@ -60,35 +309,50 @@ pub async fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<
             }
         }
         // Exit application on `Ctrl-C`
-        KeyCode::Char('c') | KeyCode::Char('C') => {
-            if key_event.modifiers == KeyModifiers::CONTROL {
-                app.quit();
-            }
+        KeyCode::Char('c') | KeyCode::Char('C') if key_event.modifiers == KeyModifiers::CONTROL => {
+            app.quit();
         },
-        KeyCode::Char('d') => {
+        // Half a page down
+        KeyCode::Char('d') if key_event.modifiers == KeyModifiers::CONTROL => {
+            scroll_page(app, 1, 2);
+        }
+        // Half a page up
+        KeyCode::Char('u') if key_event.modifiers == KeyModifiers::CONTROL => {
+            scroll_page(app, -1, 2);
+        }
+        KeyCode::PageDown => {
+            scroll_page(app, 1, 1);
+        }
+        KeyCode::PageUp => {
+            scroll_page(app, -1, 1);
+        }
+        // Toggle showing only unread articles
+        KeyCode::Char('u') => {
+            if let AppState::Normal = app.mode {
+                app.unread_only = !app.unread_only;
+                app.update_view();
+            }
+        }
+        // Go to the top
+        KeyCode::Char('g') => {
             match &mut app.mode {
-                AppState::Detail(dtl) if key_event.modifiers == KeyModifiers::CONTROL => {
-                    let mn = dtl.scroll_index + (0.8*(app.area.height as f64 - 3.0)) as usize;
-                    dtl.scroll_index = mn.min(dtl.content.lines().count());    
-                }
+                AppState::Normal => app.selected_article_index = 0,
+                AppState::Detail(dtl) => dtl.scroll_index = 0,
                 _ => {}
             }
         }
-        KeyCode::Char('u') => {
+        // Go to the bottom
+        KeyCode::Char('G') => {
+            let page = detail_height(app);
             match &mut app.mode {
-                AppState::Detail(dtl) if key_event.modifiers == KeyModifiers::CONTROL => {
-                    let mn: i64 = dtl.scroll_index as i64 - (0.8*(app.area.height as f64 - 3.0)) as i64;
-                    dtl.scroll_index = mn.max(0) as usize;    
-                }
+                AppState::Normal => app.selected_article_index = app.view.len().saturating_sub(1),
+                AppState::Detail(dtl) => dtl.scroll_index = dtl.content.lines.len().saturating_sub(page),
                 _ => {}
             }
         }
         KeyCode::Char('j')  => {
             match &mut app.mode {
-                AppState::Normal => {
-                    let temp: i64 = app.selected_article_index as i64;  
-                    app.selected_article_index = (temp + 1).min(app.articles.len() as i64 - 1) as usize
-                }
+                AppState::Normal => app.move_selection(1),
                 AppState::Detail(di) => {
                     di.scroll_index += 1
                 }
@@ -97,10 +361,7 @@ pub async fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<
         },
         KeyCode::Char('k') => {
             match &mut app.mode { 
-                AppState::Normal => {
-                    let temp: i64 = app.selected_article_index as i64;
-                    app.selected_article_index = (temp - 1).max(0) as usize;
-                },
+                AppState::Normal => app.move_selection(-1),
                 AppState::Detail(di) => {
                     let tmp = (di.scroll_index as i64 - 1).max(0);
                     di.scroll_index = tmp as usize
@@ -108,44 +369,224 @@ pub async fn handle_key_events(key_event: KeyEvent, app: &mut App) -> AppResult<
                 _ => {}
             }
         },
+        // Open the article, or the page followed from it, in the web browser
         KeyCode::Char('o') => {
-            let _ = Command::new("open")
-                .arg("-a")
-                .arg("Safari")
-                .arg(app.articles[app.selected_article_index].link.clone())
-                .output();
+            let url = match &app.mode {
+                AppState::Detail(dtl) => dtl.url.clone(),
+                _ => match app.selected_article() {
+                    Some(article) => article.link.clone(),
+                    None => return Ok(()),
+                },
+            };
+            let command = browser_command(app.config.settings.browser.as_deref(), &url);
+            // Not counted as loading, browsers often only exit when they are closed
+            if app.network.send(NetworkEvent::OpenBrowser(command)).is_err() {
+                app.show_message("Could not start the browser".to_string());
+            }
+            if let AppState::Normal = app.mode {
+                app.set_read(app.selected_article_index..app.selected_article_index + 1, true);
+            }
         },
+        // Toggle read on the selected article
+        KeyCode::Char('m') => {
+            if let (AppState::Normal, Some(article)) = (&app.mode, app.selected_article()) {
+                let read = !article.read;
+                app.set_read(app.selected_article_index..app.selected_article_index + 1, read);
+            }
+        }
+        // Star the article, keeping a copy of it
+        KeyCode::Char('s') => {
+            match &app.mode {
+                AppState::Normal => {
+                    let Some(article) = app.selected_article().cloned() else { return Ok(()) };
+                    let content = if article.starred { None } else { request_article_content(app, &article) };
+                    app.set_starred(&article.key(), !article.starred, content);
+                }
+                AppState::Detail(dtl) => {
+                    let article = dtl.article.clone();
+                    let starred = app.articles.iter().any(|x| x.key() == article.key() && x.starred);
+                    let content = if dtl.embedded {
+                        // The feed's copy may only be a teaser, keep the web page
+                        if starred { None } else { request_article_content(app, &article) }
+                    } else {
+                        // Still loading: the copy is saved when the download arrives
+                        let article_html = dtl.back.first().map_or(&dtl.html, |x| &x.html);
+                        (!article_html.is_empty()).then(|| article_html.clone())
+                    };
+                    app.set_starred(&article.key(), !starred, content);
+                }
+                _ => {}
+            }
+        }
+        // Swap the feed's copy of the article for the web page
+        KeyCode::Char('w') => {
+            let AppState::Detail(dtl) = &app.mode else { return Ok(()) };
+            if !dtl.embedded || !dtl.back.is_empty() {
+                return Ok(());
+            }
+            let article = dtl.article.clone();
+            let html = request_article_content(app, &article);
+            let (width, images) = (detail_width(app), app.show_images);
+            if let AppState::Detail(dtl) = &mut app.mode {
+                dtl.embedded = false;
+                dtl.loading = html.is_none();
+                dtl.html = html.unwrap_or_default();
+                render_detail_content(dtl, width, images);
+            }
+        }
+        // Search the headlines, or the open page
+        KeyCode::Char('/') => {
+            match &mut app.mode {
+                AppState::Normal => {
+                    app.mode = AppState::Search;
+                    app.search = Some(Search::default());
+                }
+                AppState::Detail(dtl) => {
+                    dtl.searching = true;
+                    dtl.search = Some(Search::default());
+                    dtl.update_hits();
+                }
+                _ => {}
+            }
+        }
+        KeyCode::Char('n') | KeyCode::Char('N') => {
+            let forward = key_event.code == KeyCode::Char('n');
+            match &mut app.mode {
+                AppState::Normal => app.select_match(forward),
+                AppState::Detail(dtl) if !dtl.hits.is_empty() => {
+                    let count = dtl.hits.len();
+                    let hit = if forward { (dtl.hit + 1) % count } else { (dtl.hit + count - 1) % count };
+                    dtl.select_hit(hit);
+                }
+                _ => {}
+            }
+        }
+        KeyCode::Esc => {
+            match &mut app.mode {
+                AppState::Normal => app.search = None,
+                AppState::Detail(dtl) => {
+                    dtl.search = None;
+                    dtl.update_hits();
+                }
+                _ => {}
+            }
+        }
+        // Toggle images in articles
+        KeyCode::Char('i') => {
+            app.show_images = !app.show_images;
+            rerender_detail(app);
+        }
+        // Show or hide the preview of the selected article
+        KeyCode::Char('p') => {
+            app.preview = !app.preview;
+        }
+        // Open or close the sidebar of feeds and tags
+        KeyCode::Char('b') => {
+            app.sidebar = !app.sidebar;
+            rerender_detail(app);
+        }
+        // Limit the headlines to the previous/next feed or tag in the sidebar
+        KeyCode::Char('[') | KeyCode::Char(']') => {
+            if let AppState::Normal = app.mode {
+                app.step_scope(if key_event.code == KeyCode::Char(']') { 1 } else { -1 });
+            }
+        }
+        // Show the status of every feed
+        KeyCode::Char('F') => {
+            if let AppState::Normal = app.mode {
+                app.mode = AppState::Feeds;
+            }
+        }
+        // Toggle the saved articles view
+        KeyCode::Char('S') => {
+            if let AppState::Normal = app.mode {
+                app.saved_only = !app.saved_only;
+                app.update_view();
+            }
+        }
+        // Mark everything above the selected article as read
+        KeyCode::Char('M') => {
+            if let AppState::Normal = app.mode {
+                app.set_read(0..app.selected_article_index, true);
+            }
+        }
         KeyCode::Char('r') => {
             app.selected_article_index = 0;
             app.mode = AppState::Normal;
-            download_feed(app).await;
+            request_feed_load(app);
         }
         KeyCode::Enter => {
             match app.mode {
                 AppState::Normal => {
-                    let article = app.articles[app.selected_article_index].clone();
-                    let content = download_article_detail(&article)
-                        .map_or(None, |x| parse_article_detail(&x, app.area.width - 3)).unwrap_or("Could not download article".to_string()); // sub the line no
-                    let scroll_index = find_line_with_substring(&content, &article.title);
-                    app.mode = AppState::Detail(AppDetail { article, content, scroll_index });
+                    let Some(article) = app.selected_article().cloned() else { return Ok(()) };
+                    app.set_read(app.selected_article_index..app.selected_article_index + 1, true);
+                    let embedded = !article.saved
+                        && !article.summary.trim().is_empty()
+                        && app.config.feed_content(&article.feed);
+                    let mut dtl = if embedded {
+                        let summary = article.summary.clone();
+                        AppDetail { embedded, ..AppDetail::new(article, Some(summary)) }
+                    } else {
+                        let html = request_article_content(app, &article);
+                        AppDetail::new(article, html)
+                    };
+                    render_detail_content(&mut dtl, detail_width(app), app.show_images);
+                    app.graphics.clear();
+                    app.mode = AppState::Detail(Box::new(dtl));
                 }
                 AppState::Jump(cv) => {
-                    app.selected_article_index = cv;
+                    app.selected_article_index = cv.min(app.view.len().saturating_sub(1));
                     app.mode = AppState::Normal;
                 }
+                AppState::Detail(_) => follow_link(app),
                 _ => {}
             }
         }
+        // Cycle through the links of the article
+        KeyCode::Tab | KeyCode::BackTab => {
+            if let AppState::Detail(dtl) = &mut app.mode {
+                let count = dtl.links.len();
+                if count > 0 {
+                    let next = match (dtl.selected_link, key_event.code) {
+                        (Some(current), KeyCode::Tab) => current % count + 1,
+                        (Some(current), _) => (current + count - 2) % count + 1,
+                        (None, KeyCode::Tab) => 1,
+                        (None, _) => count,
+                    };
+                    dtl.link_input = None;
+                    select_link(dtl, next);
+                }
+            }
+        }
         KeyCode::Backspace => {
-            if let AppState::Jump(cv) = app.mode {
-                app.mode = AppState::Jump(cv / 10); 
+            let (width, images) = (detail_width(app), app.show_images);
+            match &mut app.mode {
+                AppState::Jump(cv) => app.mode = AppState::Jump(*cv / 10),
+                AppState::Detail(dtl) => match dtl.link_input {
+                    Some(input) => {
+                        dtl.link_input = Some(input / 10).filter(|x| *x > 0);
+                        select_link(dtl, input / 10);
+                    }
+                    None => go_back(dtl, width, images),
+                },
+                _ => {}
             }
         }
         KeyCode::Char(v) if v.is_numeric() => {
             let numeric = (v as usize) - '0' as usize;
-            match app.mode {
+            match &mut app.mode {
+                // Typing a link number picks that link
+                AppState::Detail(dtl) => {
+                    let input = dtl.link_input.unwrap_or(0) * 10 + numeric;
+                    // A digit going past the last link starts a new number
+                    let input = if input <= dtl.links.len() { input } else { numeric };
+                    if input <= dtl.links.len() {
+                        dtl.link_input = Some(input);
+                        select_link(dtl, input);
+                    }
+                }
                 AppState::Jump(cv) => {
-                    let ncv = cv * 10 + numeric;
+                    let ncv = cv.saturating_mul(10).saturating_add(numeric);
                     app.mode = AppState::Jump(ncv);
                 }, 
                 _ => { app.mode = AppState::Jump(numeric) } 
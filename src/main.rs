@@ -1,12 +1,12 @@
 use std::io;
 
-use event::NetworkHandler;
-use handler::download_feed;
+use handler::{handle_network_event, handle_resize, request_feed_load};
 use ratatui::{backend::CrosstermBackend, Terminal};
 
 use crate::{
     app::{App, AppResult},
-    event::{Event, EventHandler},
+    config::Config,
+    event::{Event, EventHandler, NetworkHandler},
     handler::handle_key_events,
     tui::Tui,
 };
@@ -17,35 +17,86 @@ pub mod handler;
 pub mod tui;
 pub mod ui;
 pub mod article;
+pub mod browser;
+pub mod cache;
+pub mod config;
+pub mod date;
+pub mod extract;
 pub mod feedloader;
+pub mod graphics;
+pub mod opml;
+pub mod render;
+pub mod search;
+pub mod store;
+
+/// Runs a command line subcommand instead of the TUI. Returns false if there was none.
+fn run_command(args: &[String]) -> AppResult<bool> {
+    match args.first().map(String::as_str) {
+        Some("import-opml") => {
+            let path = args.get(1).ok_or("usage: newsterm import-opml <file>")?;
+            let mut config = Config::load()?;
+            let added = opml::import_opml(&mut config, &std::fs::read_to_string(path)?)?;
+            Config::append_feeds(&config.feeds[config.feeds.len() - added..])?;
+            println!("Imported {added} feeds into {}", config::config_path().display());
+        }
+        Some("export-opml") => {
+            let out = opml::export_opml(&Config::load()?);
+            match args.get(1) {
+                Some(path) => std::fs::write(path, out)?,
+                None => print!("{out}"),
+            }
+        }
+        Some(other) => {
+            return Err(format!("unknown command `{other}`, expected import-opml or export-opml").into())
+        }
+        None => return Ok(false),
+    }
+    Ok(true)
+}
 
 #[tokio::main]
 async fn main() -> AppResult<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if run_command(&args)? {
+        return Ok(());
+    }
+
     // Create an application.
-    let mut app = App::new();
+    let mut network = NetworkHandler::new();
+    let mut app = App::new(Config::load()?, network.sender());
+    match store::load_articles() {
+        Ok(articles) => app.articles = articles,
+        Err(e) => app.show_message(format!("Could not read the article history: {e}")),
+    }
+    app.update_view();
     // Initialize the terminal user interface.
     let backend = CrosstermBackend::new(io::stdout());
     let terminal = Terminal::new(backend)?;
     let events = EventHandler::new(250);
     let mut tui = Tui::new(terminal, events);
     tui.init()?;
-    download_feed(&mut app).await ;
+    request_feed_load(&mut app);
     // Start the main loop.
     while app.running {
         // Render the user interface.
         tui.draw(&mut app)?;
         // Handle events.
-        match tui.events.next().await? {
-            Event::Tick => app.tick().await,
-            Event::Key(key_event) => handle_key_events(key_event, &mut app).await?,
-            Event::Mouse(_) => {}
-            Event::Resize(_, _) => {}
+        tokio::select! {
+            event = tui.events.next() => match event? {
+                Event::Tick => app.tick().await,
+                Event::Key(key_event) => handle_key_events(key_event, &mut app).await?,
+                Event::Mouse(_) => {}
+                Event::Resize(width, _) => handle_resize(width, &mut app),
+            },
+            network_event = network.next() => handle_network_event(network_event?, &mut app),
         }
+        app.request_images();
 
         
     }
 
     // Exit the user interface.
     tui.exit()?;
+    app.flush_history().await;
     Ok(())
 }
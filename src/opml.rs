@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+
+use crate::{app::AppResult, config::Config, feedloader::Feed};
+
+/**
+ * Import
+*/
+fn outline_title<'a>(node: &roxmltree::Node<'a, 'a>) -> Option<&'a str> {
+    node.attribute("title")
+        .or_else(|| node.attribute("text"))
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+}
+
+/// Walks the outline tree, turning every outline with an `xmlUrl` into a feed
+/// tagged with the path of folders it is nested in.
+fn collect_feeds(node: roxmltree::Node, folders: &mut Vec<String>, feeds: &mut Vec<Feed>) {
+    for outline in node.children().filter(|x| x.has_tag_name("outline")) {
+        let title = outline_title(&outline);
+        let Some(url) = outline.attribute("xmlUrl") else {
+            // Folder
+            folders.push(title.unwrap_or("Untitled").to_string());
+            collect_feeds(outline, folders, feeds);
+            folders.pop();
+            continue;
+        };
+
+        let mut feed = Feed::new(url.trim());
+        feed.name = title.filter(|x| *x != feed.url).map(str::to_string);
+        if !folders.is_empty() {
+            feed.tags.push(folders.join("/"));
+        }
+        for category in outline.attribute("category").unwrap_or("").split(',') {
+            let category = category.trim().trim_start_matches('/');
+            if !category.is_empty() && !feed.tags.iter().any(|x| x == category) {
+                feed.tags.push(category.to_string());
+            }
+        }
+        feeds.push(feed);
+
+        // Some exporters nest feeds inside feeds; keep those too.
+        collect_feeds(outline, folders, feeds);
+    }
+}
+
+pub fn parse_opml(content: &str) -> AppResult<Vec<Feed>> {
+    let document = roxmltree::Document::parse(content)?;
+    let body = document
+        .root_element()
+        .children()
+        .find(|x| x.has_tag_name("body"))
+        .ok_or("OPML document has no <body>")?;
+
+    let mut feeds = Vec::new();
+    collect_feeds(body, &mut Vec::new(), &mut feeds);
+    Ok(feeds)
+}
+
+/// Adds the feeds of an OPML file to the config, skipping urls that are already subscribed.
+/// Returns the number of feeds added.
+pub fn import_opml(config: &mut Config, content: &str) -> AppResult<usize> {
+    let mut added = 0;
+    for feed in parse_opml(content)? {
+        if config.feeds.iter().any(|x| x.url == feed.url) {
+            continue;
+        }
+        config.feeds.push(feed);
+        added += 1;
+    }
+    Ok(added)
+}
+
+/**
+ * Export
+*/
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Folder tree built from the first tag of every feed, split on `/`.
+#[derive(Default)]
+struct Folder<'a> {
+    folders: BTreeMap<&'a str, Folder<'a>>,
+    feeds: Vec<&'a Feed>,
+}
+
+impl<'a> Folder<'a> {
+    fn insert(&mut self, path: &[&'a str], feed: &'a Feed) {
+        match path.split_first() {
+            Some((first, rest)) => self.folders.entry(first).or_default().insert(rest, feed),
+            None => self.feeds.push(feed),
+        }
+    }
+
+    fn write(&self, out: &mut String, depth: usize) {
+        let indent = "  ".repeat(depth);
+        for (name, folder) in &self.folders {
+            let name = escape(name);
+            out.push_str(&format!("{indent}<outline text=\"{name}\" title=\"{name}\">\n"));
+            folder.write(out, depth + 1);
+            out.push_str(&format!("{indent}</outline>\n"));
+        }
+        for feed in &self.feeds {
+            let title = escape(feed.name.as_deref().unwrap_or(&feed.url));
+            let url = escape(&feed.url);
+            let categories = feed.tags.iter().skip(1).map(|x| escape(x)).collect::<Vec<_>>().join(",");
+            let category = if categories.is_empty() { String::new() } else { format!(" category=\"{categories}\"") };
+            out.push_str(&format!(
+                "{indent}<outline type=\"rss\" text=\"{title}\" title=\"{title}\" xmlUrl=\"{url}\"{category}/>\n"
+            ));
+        }
+    }
+}
+
+pub fn export_opml(config: &Config) -> String {
+    let mut root = Folder::default();
+    for feed in &config.feeds {
+        let path: Vec<&str> = feed
+            .tags
+            .first()
+            .map(|x| x.split('/').filter(|x| !x.is_empty()).collect())
+            .unwrap_or_default();
+        root.insert(&path, feed);
+    }
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+    out.push_str("  <head>\n    <title>newsterm subscriptions</title>\n  </head>\n  <body>\n");
+    root.write(&mut out, 2);
+    out.push_str("  </body>\n</opml>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Settings;
+
+    /// Url, name and tags of every feed, sorted by url.
+    fn summary(feeds: &[Feed]) -> Vec<(String, Option<String>, Vec<String>)> {
+        let mut summary: Vec<_> = feeds.iter().map(|x| (x.url.clone(), x.name.clone(), x.tags.clone())).collect();
+        summary.sort();
+        summary
+    }
+
+    fn feed(url: &str, name: Option<&str>, tags: &[&str]) -> (String, Option<String>, Vec<String>) {
+        (url.to_string(), name.map(str::to_string), tags.iter().map(|x| x.to_string()).collect())
+    }
+
+    #[test]
+    fn imports_folders_titles_and_categories() {
+        let opml = r#"<?xml version="1.0"?>
+<opml version="1.0">
+  <head><title>Subscriptions</title></head>
+  <body>
+    <outline text="Plain" xmlUrl=" https://plain.example/rss "/>
+    <outline text="Tech">
+      <outline title="AI">
+        <outline text="Text only" title="Model &amp; Co" xmlUrl="https://ai.example/feed?a=1&amp;b=2" category="/Research, Papers,Research"/>
+      </outline>
+      <outline type="rss" text="https://gadgets.example/atom" xmlUrl="https://gadgets.example/atom"/>
+    </outline>
+    <outline title="" text=""><outline text="Deep" xmlUrl="https://deep.example/rss"/></outline>
+  </body>
+</opml>"#;
+        assert_eq!(
+            summary(&parse_opml(opml).unwrap()),
+            [
+                feed("https://ai.example/feed?a=1&b=2", Some("Model & Co"), &["Tech/AI", "Research", "Papers"]),
+                feed("https://deep.example/rss", Some("Deep"), &["Untitled"]),
+                feed("https://gadgets.example/atom", None, &["Tech"]),
+                feed("https://plain.example/rss", Some("Plain"), &[]),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_documents_without_a_body() {
+        assert!(parse_opml("<opml version=\"2.0\"><head/></opml>").is_err());
+        assert!(parse_opml("not xml").is_err());
+    }
+
+    #[test]
+    fn export_then_import_gives_the_same_feeds() {
+        let mut feeds = vec![
+            Feed::new("https://plain.example/rss"),
+            Feed::new("https://ai.example/feed?a=1&b=2"),
+            Feed::new("https://news.example/rss"),
+            Feed::new("https://quotes.example/rss"),
+        ];
+        feeds[1].name = Some("Model & Co <\"AI\">".to_string());
+        feeds[1].tags = vec!["Tech/AI".to_string(), "Research".to_string(), "Papers".to_string()];
+        feeds[2].name = Some("News".to_string());
+        feeds[2].tags = vec!["News".to_string()];
+        feeds[3].tags = vec!["Tech".to_string()];
+        let config = Config { settings: Settings::default(), feeds };
+
+        let exported = export_opml(&config);
+        assert_eq!(summary(&parse_opml(&exported).unwrap()), summary(&config.feeds));
+    }
+}
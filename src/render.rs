@@ -0,0 +1,581 @@
+//! HTML to text renderer.
+//!
+//! Turns an HTML document into lines of styled text wrapped to the width of the
+//! detail view, keeping headings, emphasis, lists, quotes and code blocks.
+
+use std::collections::HashSet;
+
+use ego_tree::{NodeId, NodeRef};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+};
+use reqwest::Url;
+use scraper::{node::Element, Html, Node};
+use unicode_width::UnicodeWidthStr;
+
+use crate::extract::{extract, Extraction};
+
+/// Elements whose content is never shown.
+const SKIPPED: &[&str] = &[
+    "head", "script", "style", "noscript", "template", "svg", "canvas", "iframe", "object", "video",
+    "audio", "input", "button", "select", "textarea",
+];
+
+/// Lines left blank for every image.
+const IMAGE_HEIGHT: usize = 12;
+
+/// Blocks separated from their surroundings by a blank line.
+const PARAGRAPHS: &[&str] = &[
+    "p", "h1", "h2", "h3", "h4", "h5", "h6", "ul", "ol", "dl", "blockquote", "pre", "table", "figure",
+];
+
+/// Blocks that only start on a new line.
+const BLOCKS: &[&str] = &[
+    "div", "section", "article", "main", "header", "footer", "nav", "aside", "li", "tr", "dt", "dd",
+    "figcaption", "address", "details", "summary", "center", "form", "fieldset", "caption",
+];
+
+fn element_style(name: &str) -> Style {
+    match name {
+        "h1" => Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        "h2" => Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        "h3" | "h4" | "h5" | "h6" | "b" | "strong" | "th" | "dt" => Style::default().add_modifier(Modifier::BOLD),
+        "i" | "em" | "cite" | "figcaption" => Style::default().add_modifier(Modifier::ITALIC),
+        "u" | "ins" => Style::default().add_modifier(Modifier::UNDERLINED),
+        "s" | "del" | "strike" => Style::default().add_modifier(Modifier::CROSSED_OUT),
+        "code" | "kbd" | "samp" | "tt" | "pre" => Style::default().fg(Color::Yellow),
+        "a" => Style::default().fg(Color::Blue).add_modifier(Modifier::UNDERLINED),
+        "mark" => Style::default().add_modifier(Modifier::REVERSED),
+        "blockquote" => Style::default().add_modifier(Modifier::ITALIC),
+        _ => Style::default(),
+    }
+}
+
+/// A run of text without whitespace, possibly in several styles.
+#[derive(Default)]
+struct Word {
+    pieces: Vec<(String, Style)>,
+    width: usize,
+    /// Footnotes whose `[n]` marker is part of the word.
+    links: Vec<usize>,
+}
+
+impl Word {
+    fn push(&mut self, c: char, style: Style) {
+        match self.pieces.last_mut() {
+            Some((text, last)) if *last == style => text.push(c),
+            _ => self.pieces.push((c.to_string(), style)),
+        }
+        self.width += char_width(c);
+    }
+}
+
+struct Renderer {
+    width: usize,
+    lines: Vec<Line<'static>>,
+    style: Style,
+    /// Words of the paragraph being built.
+    words: Vec<Word>,
+    word: Word,
+    /// Indentation of the enclosing lists and quotes.
+    prefixes: Vec<Span<'static>>,
+    /// Replaces the innermost prefix on the next line, e.g. a list bullet.
+    bullet: Option<String>,
+    /// Item counters of the enclosing lists, `None` for unordered ones.
+    lists: Vec<Option<usize>>,
+    /// Raw text of the code block being built.
+    pre: Option<Vec<(String, Style)>>,
+    /// Nodes left out by content extraction.
+    removed: HashSet<NodeId>,
+    /// Address of the page, links are only numbered when it is known.
+    base: Option<Url>,
+    links: Vec<String>,
+    /// Line where each link's marker first appears, set when its paragraph is laid out.
+    link_lines: Vec<Option<usize>>,
+    /// Images to leave space for, `None` to only show their alt text.
+    images: Option<Vec<ImageSlot>>,
+}
+
+impl Renderer {
+    fn new(width: usize) -> Self {
+        Self {
+            width,
+            lines: Vec::new(),
+            style: Style::default(),
+            words: Vec::new(),
+            word: Word::default(),
+            prefixes: Vec::new(),
+            bullet: None,
+            lists: Vec::new(),
+            pre: None,
+            removed: HashSet::new(),
+            base: None,
+            links: Vec::new(),
+            link_lines: Vec::new(),
+            images: None,
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if let Some(pre) = &mut self.pre {
+            pre.push((text.to_string(), self.style));
+            return;
+        }
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.end_word();
+            } else {
+                self.word.push(c, self.style);
+            }
+        }
+    }
+
+    fn end_word(&mut self) {
+        if !self.word.pieces.is_empty() {
+            self.words.push(std::mem::take(&mut self.word));
+        }
+    }
+
+    /// Spans to start the next line with, using up the pending bullet.
+    fn take_prefix(&mut self) -> Vec<Span<'static>> {
+        let mut prefix = self.prefixes.clone();
+        if let Some(bullet) = self.bullet.take() {
+            if let Some(last) = prefix.last_mut() {
+                let width = last.width().max(bullet.width());
+                *last = Span::raw(format!("{bullet:width$}"));
+            }
+        }
+        prefix
+    }
+
+    fn prefix_width(&self) -> usize {
+        self.prefixes.iter().map(|x| x.width()).sum()
+    }
+
+    fn push_line(&mut self, spans: Vec<Span<'static>>) {
+        let mut line = self.take_prefix();
+        line.extend(spans);
+        self.lines.push(Line::from(line));
+    }
+
+    /// Wraps the words collected so far into lines.
+    fn flush_paragraph(&mut self) {
+        self.end_word();
+        if self.words.is_empty() {
+            return;
+        }
+        let available = self.width.saturating_sub(self.prefix_width()).max(10);
+        let mut line: Vec<Span<'static>> = Vec::new();
+        let mut line_width = 0;
+        for word in std::mem::take(&mut self.words) {
+            if line_width > 0 && line_width + 1 + word.width > available {
+                let full = std::mem::take(&mut line);
+                self.push_line(full);
+                line_width = 0;
+            }
+            if line_width > 0 {
+                push_char(&mut line, ' ', Style::default());
+                line_width += 1;
+            }
+            for number in &word.links {
+                self.mark_link(*number, self.lines.len());
+            }
+            // Only words longer than a whole line get split here
+            for (text, style) in word.pieces {
+                for c in text.chars() {
+                    let char_width = char_width(c);
+                    if line_width > 0 && line_width + char_width > available {
+                        let full = std::mem::take(&mut line);
+                        self.push_line(full);
+                        line_width = 0;
+                    }
+                    push_char(&mut line, c, style);
+                    line_width += char_width;
+                }
+            }
+        }
+        if !line.is_empty() {
+            self.push_line(line);
+        }
+    }
+
+    /// Lays out a code block as is, only breaking lines that are too long.
+    fn flush_pre(&mut self, pieces: Vec<(String, Style)>) {
+        let mut chars: Vec<(char, Style)> = pieces
+            .iter()
+            .flat_map(|(text, style)| text.chars().map(|c| (c, *style)))
+            .filter(|(c, _)| *c != '\r')
+            .collect();
+        while chars.last().is_some_and(|(c, _)| *c == '\n') {
+            chars.pop();
+        }
+        let start = chars.iter().position(|(c, _)| *c != '\n').unwrap_or(chars.len());
+
+        let available = self.width.saturating_sub(self.prefix_width()).max(10);
+        let mut line: Vec<Span<'static>> = Vec::new();
+        let mut line_width = 0;
+        for (c, style) in chars.into_iter().skip(start) {
+            if c == '\n' {
+                let full = std::mem::take(&mut line);
+                self.push_line(full);
+                line_width = 0;
+                continue;
+            }
+            let c = if c == '\t' { ' ' } else { c };
+            if line_width > 0 && line_width + char_width(c) > available {
+                let full = std::mem::take(&mut line);
+                self.push_line(full);
+                line_width = 0;
+            }
+            push_char(&mut line, c, style);
+            line_width += char_width(c);
+        }
+        if !line.is_empty() {
+            self.push_line(line);
+        }
+    }
+
+    fn blank_line(&mut self) {
+        if self.lines.last().is_some_and(|x| x.width() > 0) {
+            self.lines.push(Line::default());
+        }
+    }
+
+    fn walk_children(&mut self, node: NodeRef<'_, Node>) {
+        for child in node.children() {
+            self.walk(child);
+        }
+    }
+
+    fn walk(&mut self, node: NodeRef<'_, Node>) {
+        if self.removed.contains(&node.id()) {
+            return;
+        }
+        let element = match node.value() {
+            Node::Text(text) => return self.push_text(text),
+            Node::Document | Node::Fragment => return self.walk_children(node),
+            Node::Element(element) => element,
+            _ => return,
+        };
+        let name = element.name();
+        if SKIPPED.contains(&name) {
+            return;
+        }
+
+        match name {
+            "br" => {
+                self.flush_paragraph();
+                if let Some(pre) = &mut self.pre {
+                    pre.push(("\n".to_string(), self.style));
+                }
+                return;
+            }
+            "hr" => {
+                self.flush_paragraph();
+                self.blank_line();
+                let width = self.width.saturating_sub(self.prefix_width()).min(40);
+                self.push_line(vec![Span::styled("─".repeat(width), Style::default().fg(Color::DarkGray))]);
+                self.blank_line();
+                return;
+            }
+            "img" => {
+                let alt = element.attr("alt").map(str::trim).filter(|x| !x.is_empty());
+                if let Some(url) = self.image_url(element) {
+                    self.push_image(url, alt);
+                } else if let Some(alt) = alt {
+                    let style = self.style;
+                    self.style = style.fg(Color::DarkGray);
+                    self.push_text(&format!(" [image: {alt}] "));
+                    self.style = style;
+                }
+                return;
+            }
+            "td" | "th" => self.push_text(" "),
+            _ => {}
+        }
+
+        // Inside lists blocks are kept together, without blank lines
+        let spaced = PARAGRAPHS.contains(&name) && self.lists.is_empty();
+        let block = PARAGRAPHS.contains(&name) || BLOCKS.contains(&name);
+        if block {
+            self.flush_paragraph();
+        }
+        if spaced {
+            self.blank_line();
+        }
+
+        let style = self.style;
+        self.style = style.patch(element_style(name));
+        let prefixes = self.prefixes.len();
+        let lists = self.lists.len();
+        match name {
+            "ul" => self.lists.push(None),
+            "ol" => self.lists.push(Some(element.attr("start").and_then(|x| x.parse().ok()).unwrap_or(1))),
+            "li" => {
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(counter)) => {
+                        *counter += 1;
+                        format!("{}. ", *counter - 1)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.prefixes.push(Span::raw(" ".repeat(bullet.width())));
+                self.bullet = Some(bullet);
+            }
+            "blockquote" => self.prefixes.push(Span::styled("│ ", Style::default().fg(Color::DarkGray))),
+            "dd" => self.prefixes.push(Span::raw("    ")),
+            "pre" => {
+                self.prefixes.push(Span::raw("  "));
+                self.pre = Some(Vec::new());
+            }
+            _ => {}
+        }
+
+        self.walk_children(node);
+
+        if name == "a" {
+            self.push_link_marker(element.attr("href"));
+        }
+        if name == "pre" {
+            let pieces = self.pre.take().unwrap_or_default();
+            self.flush_pre(pieces);
+        }
+        if block {
+            self.flush_paragraph();
+        }
+        if name == "li" {
+            self.bullet = None;
+        }
+        self.prefixes.truncate(prefixes);
+        self.lists.truncate(lists);
+        self.style = style;
+        if spaced {
+            self.blank_line();
+        }
+    }
+}
+
+impl Renderer {
+    /// Appends the footnote number of a link to its text.
+    fn push_link_marker(&mut self, href: Option<&str>) {
+        let Some(url) = self.base.as_ref().zip(href).and_then(|(base, href)| base.join(href.trim()).ok()) else { return };
+        if !matches!(url.scheme(), "http" | "https") {
+            return;
+        }
+        let url = url.to_string();
+        let number = match self.links.iter().position(|x| *x == url) {
+            Some(idx) => idx + 1,
+            None => {
+                self.links.push(url);
+                self.links.len()
+            }
+        };
+        let style = self.style;
+        self.style = Style::default().fg(Color::DarkGray);
+        self.push_text(&format!("[{number}]"));
+        self.style = style;
+        if self.pre.is_some() {
+            // Code blocks are laid out from the next line on
+            self.mark_link(number, self.lines.len());
+        } else {
+            self.word.links.push(number);
+        }
+    }
+
+    /// Records `line` as where link `number` first appears.
+    fn mark_link(&mut self, number: usize, line: usize) {
+        if self.link_lines.len() < number {
+            self.link_lines.resize(number, None);
+        }
+        self.link_lines[number - 1].get_or_insert(line);
+    }
+
+    /// Address of an `<img>` worth showing, if images are shown at all.
+    fn image_url(&self, element: &Element) -> Option<String> {
+        self.images.as_ref()?;
+        // Lazy loading pages put a placeholder in `src`
+        let src = element
+            .attr("src")
+            .filter(|x| !x.starts_with("data:"))
+            .or(element.attr("data-src"))?;
+        // Tracking pixels and icons
+        let tiny = |attr: &str| element.attr(attr).and_then(|x| x.trim_end_matches("px").parse::<u32>().ok()).is_some_and(|x| x < 32);
+        if tiny("width") || tiny("height") {
+            return None;
+        }
+        let url = self.base.as_ref()?.join(src.trim()).ok()?;
+        matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+    }
+
+    /// Leaves blank lines for an image, under its alt text.
+    fn push_image(&mut self, url: String, alt: Option<&str>) {
+        self.flush_paragraph();
+        self.blank_line();
+        let label = alt.map_or("[image]".to_string(), |alt| format!("[image: {alt}]"));
+        self.push_line(vec![Span::styled(label, Style::default().fg(Color::DarkGray))]);
+        let line = self.lines.len();
+        // Not empty, so they survive trimming at the end of the page
+        self.lines.extend(std::iter::repeat_n(Line::from(" "), IMAGE_HEIGHT));
+        if let Some(images) = &mut self.images {
+            images.push(ImageSlot { url, line, height: IMAGE_HEIGHT });
+        }
+        self.lines.push(Line::default());
+    }
+
+    /// Shows the page's `og:image` first, unless the article already has it.
+    fn push_lead_image(&mut self, document: &Html) {
+        let Some(images) = &self.images else { return };
+        let lead = document
+            .tree
+            .root()
+            .descendants()
+            .filter_map(|x| x.value().as_element())
+            .find(|x| x.name() == "meta" && x.attr("property") == Some("og:image"))
+            .and_then(|x| x.attr("content"))
+            .and_then(|x| self.base.as_ref()?.join(x.trim()).ok())
+            .map(|x| x.to_string());
+        let Some(lead) = lead.filter(|x| !images.iter().any(|image| image.url == *x)) else { return };
+
+        self.flush_paragraph();
+        let lines = std::mem::take(&mut self.lines);
+        let images = self.images.replace(Vec::new()).unwrap_or_default();
+        self.push_image(lead, None);
+        let offset = self.lines.len();
+        self.lines.extend(lines);
+        if let Some(lead) = &mut self.images {
+            lead.extend(images.into_iter().map(|x| ImageSlot { line: x.line + offset, ..x }));
+        }
+        for line in self.link_lines.iter_mut().flatten() {
+            *line += offset;
+        }
+    }
+
+    /// Lists the links at the end of the page.
+    fn push_footnotes(&mut self) {
+        if self.links.is_empty() {
+            return;
+        }
+        self.flush_paragraph();
+        self.blank_line();
+        self.lines.push(Line::styled("Links", Style::default().add_modifier(Modifier::BOLD)));
+        for (idx, url) in self.links.clone().iter().enumerate() {
+            self.style = Style::default().fg(Color::DarkGray);
+            self.push_text(&format!("[{}] ", idx + 1));
+            self.style = Style::default();
+            self.push_text(url);
+            self.flush_paragraph();
+        }
+    }
+}
+
+fn char_width(c: char) -> usize {
+    UnicodeWidthStr::width(c.encode_utf8(&mut [0; 4]) as &str)
+}
+
+/// Appends `c` to the last span of `line` if it has the same style.
+fn push_char(line: &mut Vec<Span<'static>>, c: char, style: Style) {
+    match line.last_mut() {
+        Some(span) if span.style == style => span.content.to_mut().push(c),
+        _ => line.push(Span::styled(c.to_string(), style)),
+    }
+}
+
+/// Renders an HTML document as styled text wrapped to `width` columns.
+pub fn render_html(html: &str, width: usize) -> Text<'static> {
+    let document = Html::parse_document(html);
+    let mut renderer = Renderer::new(width);
+    renderer.walk(document.tree.root());
+    finish(renderer)
+}
+
+/// A web page rendered for the detail view.
+pub struct Rendered {
+    pub text: Text<'static>,
+    /// Links of the page, footnote `[n]` is `links[n - 1]`.
+    pub links: Vec<String>,
+    /// Line of `text` where each link first appears.
+    pub link_lines: Vec<usize>,
+    pub images: Vec<ImageSlot>,
+}
+
+/// Blank lines left in a rendered page for an image.
+#[derive(Debug, Clone)]
+pub struct ImageSlot {
+    pub url: String,
+    /// First line of the space.
+    pub line: usize,
+    pub height: usize,
+}
+
+/// Renders only the main content of the web page at `url` (see [`extract`]),
+/// numbering its links and listing them at the end. With `images` space is left
+/// for the lead image and the images in the article.
+pub fn render_article(html: &str, url: &str, width: usize, images: bool) -> Rendered {
+    let document = Html::parse_document(html);
+    let extraction = extract(&document);
+    render_extraction(&document, extraction, url, width, images)
+}
+
+/// Renders the article text embedded in a feed, which has no page clutter to strip.
+pub fn render_feed_content(html: &str, url: &str, width: usize, images: bool) -> Rendered {
+    let document = Html::parse_fragment(html);
+    let extraction = Extraction { roots: vec![document.tree.root().id()], removed: HashSet::new() };
+    render_extraction(&document, extraction, url, width, images)
+}
+
+fn render_extraction(document: &Html, extraction: Extraction, url: &str, width: usize, images: bool) -> Rendered {
+    let mut renderer = Renderer::new(width);
+    renderer.removed = extraction.removed;
+    renderer.base = Url::parse(url).ok();
+    renderer.images = images.then(Vec::new);
+    for root in extraction.roots {
+        if let Some(node) = document.tree.get(root) {
+            renderer.walk(node);
+        }
+    }
+    renderer.push_lead_image(document);
+    renderer.push_footnotes();
+    let links = std::mem::take(&mut renderer.links);
+    let link_lines = std::mem::take(&mut renderer.link_lines).into_iter().map(|x| x.unwrap_or(0)).collect();
+    let images = renderer.images.take().unwrap_or_default();
+    let text = finish(renderer);
+    Rendered { text, links, link_lines, images }
+}
+
+fn finish(mut renderer: Renderer) -> Text<'static> {
+    renderer.flush_paragraph();
+    let mut lines = renderer.lines;
+    while lines.last().is_some_and(|x| x.width() == 0) {
+        lines.pop();
+    }
+    if lines.is_empty() {
+        lines.push(Line::from("No readable content"));
+    }
+    Text::from(lines)
+}
+
+/// The text of a rendered line without its styling.
+pub fn line_text(line: &Line) -> String {
+    line.spans.iter().map(|x| x.content.as_ref()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{line_text, render_feed_content};
+
+    #[test]
+    fn link_lines_point_at_the_markers() {
+        let html = "<p>A fact from a citation.[1] [2]</p>\
+            <p>Some words before the first link, enough of them to wrap onto a second line.\
+            Then <a href=\"/one\">one</a> and later <a href=\"https://example.com/two\">two</a>.</p>\
+            <pre>code with <a href=\"/three\">three</a></pre>";
+        let rendered = render_feed_content(html, "https://example.com/page", 30, false);
+        assert_eq!(rendered.links, ["https://example.com/one", "https://example.com/two", "https://example.com/three"]);
+        for (idx, line) in rendered.link_lines.iter().enumerate() {
+            let text = line_text(&rendered.text.lines[*line]);
+            assert!(text.contains(&format!("[{}]", idx + 1)), "link {} on {text:?}", idx + 1);
+            assert!(!text.contains("citation"), "link {} on {text:?}", idx + 1);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use std::ops::Range;
+
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+use regex::{Regex, RegexBuilder};
+
+use crate::article::Article;
+
+/// A search typed after `/`, matched case-insensitively.
+#[derive(Debug, Clone, Default)]
+pub struct Search {
+    pub query: String,
+    /// Treat the query as a regular expression rather than plain text.
+    pub regex: bool,
+    /// Compiled query, `None` when it is empty or not a valid regex.
+    pub pattern: Option<Regex>,
+}
+
+impl Search {
+    /// Compiles the query again after it has changed.
+    pub fn update(&mut self) {
+        let pattern = if self.regex { self.query.clone() } else { regex::escape(&self.query) };
+        self.pattern = if self.query.is_empty() {
+            None
+        } else {
+            RegexBuilder::new(&pattern).case_insensitive(true).build().ok()
+        };
+    }
+
+    /// Byte ranges of the matches in `text`.
+    pub fn find(&self, text: &str) -> Vec<Range<usize>> {
+        let Some(pattern) = &self.pattern else { return Vec::new() };
+        pattern.find_iter(text).filter(|x| !x.is_empty()).map(|x| x.range()).collect()
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.pattern.as_ref().is_some_and(|x| x.is_match(text))
+    }
+
+    /// Whether the title, publisher or summary of `article` match.
+    pub fn matches_article(&self, article: &Article) -> bool {
+        self.is_match(&article.title) || self.is_match(&article.publisher) || self.is_match(&strip_tags(&article.summary))
+    }
+
+    /// `text` in `style`, with the matches highlighted.
+    pub fn highlight(&self, text: &str, style: Style) -> Line<'static> {
+        let Some(pattern) = &self.pattern else { return Line::styled(text.to_string(), style) };
+        let mut spans = Vec::new();
+        let mut end = 0;
+        for found in pattern.find_iter(text).filter(|x| !x.is_empty()) {
+            spans.push(Span::styled(text[end..found.start()].to_string(), style));
+            spans.push(Span::styled(found.as_str().to_string(), highlight_style(style)));
+            end = found.end();
+        }
+        spans.push(Span::styled(text[end..].to_string(), style));
+        Line::from(spans)
+    }
+}
+
+pub fn highlight_style(style: Style) -> Style {
+    style.fg(Color::Black).bg(Color::Yellow)
+}
+
+/// Restyles the parts of `line` at the byte `ranges` of its text, keeping the rest of its styling.
+pub fn highlight_ranges(line: &Line<'static>, ranges: &[(Range<usize>, Style)]) -> Line<'static> {
+    if ranges.is_empty() {
+        return line.clone();
+    }
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    for span in &line.spans {
+        let content = span.content.as_ref();
+        let end = offset + content.len();
+        // Split the span wherever a range starts or ends inside it
+        let mut cuts: Vec<usize> = ranges
+            .iter()
+            .flat_map(|(range, _)| [range.start, range.end])
+            .filter(|&x| x > offset && x < end)
+            .map(|x| x - offset)
+            .collect();
+        cuts.push(0);
+        cuts.push(content.len());
+        cuts.sort_unstable();
+        cuts.dedup();
+        for piece in cuts.windows(2) {
+            let start = offset + piece[0];
+            let style = ranges
+                .iter()
+                .find(|(range, _)| range.start <= start && start < range.end)
+                .map_or(span.style, |(_, style)| span.style.patch(*style));
+            spans.push(Span::styled(content[piece[0]..piece[1]].to_string(), style));
+        }
+        offset = end;
+    }
+    Line::from(spans).style(line.style)
+}
+
+/// Rough text of an HTML snippet, good enough to search in.
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
@@ -0,0 +1,193 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use crate::{app::AppResult, article::Article, cache::hash, config::Config};
+
+/// Article history is kept in `$XDG_DATA_HOME/newsterm/articles.json`.
+fn store_path() -> PathBuf {
+    let mut path = dirs::data_dir().expect("Couldn't find data dir");
+    path.push("newsterm/articles.json");
+    path
+}
+
+/// Saved copies of starred articles, one file per article next to the history.
+fn saved_path(key: &str) -> PathBuf {
+    let mut path = store_path();
+    path.set_file_name(format!("saved/{:016x}.html", hash(key)));
+    path
+}
+
+pub fn write_saved(key: &str, content: &str) -> AppResult<()> {
+    let path = saved_path(key);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+pub fn read_saved(key: &str) -> Option<String> {
+    std::fs::read_to_string(saved_path(key)).ok()
+}
+
+pub fn remove_saved(key: &str) {
+    let _ = std::fs::remove_file(saved_path(key));
+}
+
+/// Reads the article history. A history that can't be parsed is moved aside to
+/// `articles.json.bak`, so the next save doesn't overwrite it, and reported as an error.
+pub fn load_articles() -> AppResult<Vec<Article>> {
+    let path = store_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let articles: Vec<Article> = serde_json::from_slice(&std::fs::read(&path)?).map_err(|e| -> Box<dyn std::error::Error> {
+        let backup = path.with_extension("json.bak");
+        match std::fs::rename(&path, &backup) {
+            Ok(()) => format!("{e}, moved it to {}", backup.display()).into(),
+            Err(_) => e.into(),
+        }
+    })?;
+    Ok(articles)
+}
+
+pub fn save_articles(articles: &[Article]) -> AppResult<()> {
+    let path = store_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Write then rename so a crash mid-write can't lose the history
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string(articles)?)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+/// Merges freshly downloaded articles into the history. Articles already known
+/// (by feed and id) are updated but keep their read state and first seen time.
+pub fn merge_articles(history: &mut Vec<Article>, downloaded: Vec<Article>, now: i64) {
+    let mut index: HashMap<String, usize> = history
+        .iter()
+        .enumerate()
+        .map(|(idx, article)| (article.key(), idx))
+        .collect();
+
+    for mut article in downloaded {
+        if article.id.is_empty() {
+            article.id = format!("{}\n{}", article.publisher, article.title);
+        }
+        article.last_seen = now;
+        match index.get(&article.key()) {
+            Some(&idx) => {
+                let existing = &mut history[idx];
+                article.read = existing.read;
+                article.starred = existing.starred;
+                article.saved = existing.saved;
+                article.first_seen = existing.first_seen;
+                *existing = article;
+            }
+            None => {
+                article.first_seen = now;
+                index.insert(article.key(), history.len());
+                history.push(article);
+            }
+        }
+    }
+}
+
+/// Drops articles older than their feed's `max_age`, and articles that have not been
+/// in their feed for `settings.keep_days`. Starred articles are always kept.
+pub fn prune_articles(history: &mut Vec<Article>, config: &Config, now: i64) {
+    const DAY: i64 = 24 * 60 * 60;
+    let keep = config.settings.keep_days as i64 * DAY;
+    history.retain(|article| {
+        let max_age = config
+            .feeds
+            .iter()
+            .find(|f| f.url == article.feed)
+            .map_or(config.settings.max_age, |f| config.max_age(f)) as i64
+            * DAY;
+        let too_old = max_age != 0 && now - article.time() > max_age;
+        let dropped = keep != 0 && now - article.last_seen > keep;
+        article.starred || (!too_old && !dropped)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feedloader::Feed;
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn article(feed: &str, id: &str, title: &str) -> Article {
+        Article {
+            id: id.to_string(),
+            title: title.to_string(),
+            publisher: "Publisher".to_string(),
+            feed: feed.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn the_same_id_in_two_feeds_stays_two_articles() {
+        let mut history = Vec::new();
+        merge_articles(&mut history, vec![article("https://a.example", "1", "A"), article("https://b.example", "1", "B")], 100);
+        merge_articles(&mut history, vec![article("https://b.example", "1", "B again")], 200);
+        let titles: Vec<&str> = history.iter().map(|x| x.title.as_str()).collect();
+        assert_eq!(titles, ["A", "B again"]);
+    }
+
+    #[test]
+    fn redownloads_keep_read_starred_and_first_seen() {
+        let mut history = Vec::new();
+        merge_articles(&mut history, vec![article("https://a.example", "1", "Old title")], 100);
+        history[0].read = true;
+        history[0].starred = true;
+        history[0].saved = true;
+
+        merge_articles(&mut history, vec![article("https://a.example", "1", "New title")], 200);
+        assert_eq!(history.len(), 1);
+        let article = &history[0];
+        assert_eq!(article.title, "New title");
+        assert!(article.read && article.starred && article.saved);
+        assert_eq!((article.first_seen, article.last_seen), (100, 200));
+    }
+
+    #[test]
+    fn articles_without_an_id_are_told_apart_by_publisher_and_title() {
+        let mut history = Vec::new();
+        merge_articles(&mut history, vec![article("https://a.example", "", "One"), article("https://a.example", "", "Two")], 100);
+        history[0].read = true;
+        merge_articles(&mut history, vec![article("https://a.example", "", "One")], 200);
+        let ids: Vec<(&str, bool)> = history.iter().map(|x| (x.id.as_str(), x.read)).collect();
+        assert_eq!(ids, [("Publisher\nOne", true), ("Publisher\nTwo", false)]);
+    }
+
+    #[test]
+    fn pruning_keeps_starred_articles() {
+        let now = 100 * DAY;
+        let mut config = Config::default();
+        config.settings.keep_days = 30;
+        let mut feed = Feed::new("https://a.example");
+        feed.max_age = Some(2);
+        config.feeds.push(feed);
+
+        let mut history = Vec::new();
+        for (feed, title, date, last_seen, starred) in [
+            ("https://a.example", "fresh", now - DAY, now, false),
+            ("https://a.example", "too old", now - 3 * DAY, now, false),
+            ("https://a.example", "too old but starred", now - 3 * DAY, now, true),
+            // Feeds without max_age use the settings, where 0 keeps everything
+            ("https://b.example", "old", now - 90 * DAY, now, false),
+            ("https://b.example", "dropped", now - DAY, now - 31 * DAY, false),
+            ("https://b.example", "dropped but starred", now - DAY, now - 31 * DAY, true),
+        ] {
+            history.push(Article { date: Some(date), last_seen, starred, ..article(feed, title, title) });
+        }
+
+        prune_articles(&mut history, &config, now);
+        let titles: Vec<&str> = history.iter().map(|x| x.title.as_str()).collect();
+        assert_eq!(titles, ["fresh", "too old but starred", "old", "dropped but starred"]);
+    }
+}
@@ -4,6 +4,7 @@ use crate::ui;
 use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
 use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
 use ratatui::backend::Backend;
+use ratatui::buffer::Cell;
 use ratatui::Terminal;
 use std::io;
 use std::panic;
@@ -51,7 +52,23 @@ impl<B: Backend> Tui<B> {
     /// [`Draw`]: ratatui::Terminal::draw
     /// [`rendering`]: crate::ui::render
     pub fn draw(&mut self, app: &mut App) -> AppResult<()> {
-        self.terminal.draw(|frame| ui::render(app, frame))?;
+        app.graphics.placements.clear();
+        let frame = self.terminal.draw(|frame| ui::render(app, frame))?;
+        // Images drawn with escape sequences are not part of ratatui's buffer, so they
+        // are only drawn when they move, after repainting the rows the old ones covered.
+        if app.graphics.placements == app.graphics.drawn {
+            return Ok(());
+        }
+        let buffer = frame.buffer;
+        let cells: Vec<(u16, u16, Cell)> = app
+            .graphics
+            .drawn_rows()
+            .into_iter()
+            .flat_map(|y| (buffer.area.left()..buffer.area.right()).map(move |x| (x, y)))
+            .filter_map(|(x, y)| buffer.cell((x, y)).map(|cell| (x, y, cell.clone())))
+            .collect();
+        self.terminal.backend_mut().draw(cells.iter().map(|(x, y, cell)| (*x, *y, cell)))?;
+        app.graphics.write(&mut io::stdout())?;
         Ok(())
     }
 
@@ -1,10 +1,12 @@
 
-use chrono::{Utc, TimeZone};
+use chrono::{Local, Utc};
 use ratatui::{
-    layout::{Alignment, Constraint, Layout}, style::{Color, Modifier, Style}, widgets::{Block, BorderType, Cell, Paragraph, Row, Table}, Frame
+    layout::{Alignment, Constraint, Layout, Margin, Rect}, style::{Color, Modifier, Style}, widgets::{Block, BorderType, Cell, Paragraph, Row, Table, TableState, Wrap}, Frame
 };
 
-use crate::{app::{App, AppArea, AppState}, article::Article, feedloader::Feed};
+use ratatui::text::{Line, Span, Text};
+
+use crate::{article::Article, app::{App, AppArea, AppDetail, AppState, FeedReport, Scope}, search::{highlight_ranges, highlight_style, Search}};
 
 /// Renders the user interface widgets.
 pub fn render(app: &mut App, frame: &mut Frame) {
@@ -15,75 +17,143 @@ pub fn render(app: &mut App, frame: &mut Frame) {
     //
     let area = frame.area();
     app.area = AppArea { width: area.width as usize, height: area.height as usize }; 
+    let [body, status_bar] = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+    frame.render_widget(render_status_bar(app), status_bar);
+    let body = if app.sidebar && !matches!(app.mode, AppState::Feeds) {
+        let [sidebar, body] = Layout::horizontal([Constraint::Length(app.sidebar_width() as u16), Constraint::Fill(1)]).areas(body);
+        frame.render_widget(
+            render_sidebar(app)
+            .block(
+                Block::bordered()
+                    .title("Feeds & Tags")
+                    .title_alignment(Alignment::Left)
+                    .border_type(BorderType::Rounded),
+            ),
+            sidebar,
+        );
+        body
+    } else {
+        body
+    };
     match &app.mode {
-        AppState::Normal => {
+        AppState::Normal if app.preview => {
+            // Side by side when there is room, else one above the other
+            let [headlines, preview] = if body.width >= 120 {
+                Layout::horizontal([Constraint::Percentage(55), Constraint::Fill(1)]).areas(body)
+            } else {
+                Layout::vertical([Constraint::Percentage(50), Constraint::Fill(1)]).areas(body)
+            };
+            draw_headlines(app, frame, headlines);
+            let summary = app.preview_summary(preview.width.saturating_sub(2) as usize);
             frame.render_widget(
-                render_headlines(&app.articles, app.selected_article_index, app.area.height)
+                render_preview(app.selected_article(), summary)
                 .block(
                     Block::bordered()
-                        .title("Main Feed")
+                        .title("Preview")
                         .title_alignment(Alignment::Left)
                         .border_type(BorderType::Rounded),
                 ),
-                frame.area(),
+                preview,
             );
         }
-        AppState::Detail(dtl) => {
+        AppState::Normal => {
+            draw_headlines(app, frame, body);
+        }
+        AppState::Detail(_) => {
             let constraints = vec![Constraint::Percentage(20), Constraint::Percentage(80)];
             let layout = Layout::default()
                 .direction(ratatui::layout::Direction::Vertical)
                 .constraints(constraints)
-                .split(frame.area());
+                .split(body);
             
+            draw_headlines(app, frame, layout[0]);
+
+            let AppState::Detail(dtl) = &app.mode else { return };
+            let [content, prompt] = if dtl.searching {
+                Layout::vertical([Constraint::Fill(1), Constraint::Max(3)]).areas(layout[1])
+            } else {
+                [layout[1], Rect::default()]
+            };
             frame.render_widget(
-                render_headlines(&app.articles, app.selected_article_index, app.area.height)
+                render_detail(highlight_hits(dtl), dtl.scroll_index)
                 .block(
                     Block::bordered()
-                        .title("Main Feed")
+                        .title(detail_title(dtl))
                         .title_alignment(Alignment::Left)
                         .border_type(BorderType::Rounded),
                 ),
-                layout[0],
+                content,
             );
+            if dtl.searching {
+                frame.render_widget(
+                    render_search(dtl.search.as_ref())
+                    .block(
+                        Block::bordered()
+                            .title(search_title(dtl.search.as_ref(), dtl.hits.len()))
+                            .title_alignment(Alignment::Left)
+                            .border_type(BorderType::Rounded),
+                    ),
+                    prompt,
+                );
+            }
+
+            if app.show_images {
+                let inner = content.inner(Margin { horizontal: 1, vertical: 1 });
+                for image in &dtl.images {
+                    // Only whole images are drawn
+                    let Some(top) = image.line.checked_sub(dtl.scroll_index) else { continue };
+                    if top + image.height > inner.height as usize {
+                        continue;
+                    }
+                    let area = Rect { y: inner.y + top as u16, height: image.height as u16, ..inner };
+                    app.graphics.render(&image.url, area, frame.buffer_mut());
+                }
+            }
+        },
+        &AppState::Jump(cv) => {
+            let constraints = vec![Constraint::Fill(1), Constraint::Max(3)];
+            let layout = Layout::default()
+                .direction(ratatui::layout::Direction::Vertical)
+                .constraints(constraints)
+                .split(body);
+            
+            draw_headlines(app, frame, layout[0]);
 
             frame.render_widget(
-                render_detail(dtl.content.clone(), dtl.scroll_index)
+                render_jump(cv)
                 .block(
                     Block::bordered()
-                        .title(dtl.article.title.clone())
+                        .title("Jump To Article")
                         .title_alignment(Alignment::Left)
                         .border_type(BorderType::Rounded),
                 ),
                 layout[1],
             );
-        },
-        AppState::Jump(cv) => {
-            let constraints = vec![Constraint::Fill(1), Constraint::Max(3)];
-            let layout = Layout::default()
-                .direction(ratatui::layout::Direction::Vertical)
-                .constraints(constraints)
-                .split(frame.area());
-            
+        }
+        AppState::Search => {
+            let [headlines, prompt] = Layout::vertical([Constraint::Fill(1), Constraint::Max(3)]).areas(body);
+            draw_headlines(app, frame, headlines);
             frame.render_widget(
-                render_headlines(&app.articles, app.selected_article_index, app.area.height)
+                render_search(app.search.as_ref())
                 .block(
                     Block::bordered()
-                        .title("Main Feed")
+                        .title(search_title(app.search.as_ref(), app.view.len()))
                         .title_alignment(Alignment::Left)
                         .border_type(BorderType::Rounded),
                 ),
-                layout[0],
+                prompt,
             );
-
+        }
+        AppState::Feeds => {
             frame.render_widget(
-                render_jump(*cv)
+                render_feeds(app)
                 .block(
                     Block::bordered()
-                        .title("Jump To Article")
+                        .title("Feeds")
                         .title_alignment(Alignment::Left)
                         .border_type(BorderType::Rounded),
                 ),
-                layout[1],
+                body,
             );
         }
     }
@@ -92,9 +162,7 @@ pub fn render(app: &mut App, frame: &mut Frame) {
 
 fn time_ago(timestamp: i64) -> String {
     // Convert the provided timestamp (assumed to be in seconds) to a datetime
-    let time = chrono::NaiveDateTime::from_timestamp_opt(timestamp, 0)
-        .map(|t| chrono::DateTime::<Utc>::from_utc(t, Utc))
-        .unwrap_or_else(|| Utc.timestamp(timestamp, 0));
+    let time = chrono::DateTime::<Utc>::from_timestamp(timestamp, 0).unwrap_or_default();
     let now = Utc::now();
 
     // Calculate the duration between now and the provided time
@@ -117,35 +185,276 @@ fn time_ago(timestamp: i64) -> String {
     }
 }
 
-fn render_headlines(articles: &Vec<Article>, selected_index: usize, height: usize) -> ratatui::widgets::Table {
+fn feed_title(app: &App) -> String {
+    let title = headlines_title(app);
+    if app.loading > 0 {
+        format!("{title} - Loading...")
+    } else {
+        title
+    }
+}
+
+fn headlines_title(app: &App) -> String {
+    let unread = app.unread_count(&app.scope);
+    let name = match app.scope {
+        Scope::All => "Main Feed".to_string(),
+        _ => app.scope_name(&app.scope),
+    };
+    if app.saved_only {
+        format!("Saved ({})", app.view.len())
+    } else if app.unread_only {
+        format!("{name} - Unread ({unread})")
+    } else {
+        format!("{name} ({unread} unread)")
+    }
+}
+
+fn render_sidebar(app: &App) -> Table<'_> {
+    let widths = vec![Constraint::Fill(1), Constraint::Max(5)];
+    let rows: Vec<Row> = app.scopes().into_iter().map(|scope| {
+        let unread = app.unread_count(&scope);
+        let style = if unread > 0 { Style::default().add_modifier(Modifier::BOLD) } else { Style::default().fg(Color::Rgb(128,128,128)) };
+        let row = Row::new(vec![
+            Cell::from(app.scope_name(&scope)).style(style),
+            Cell::from(Line::from(unread.to_string()).alignment(Alignment::Right)).style(Style::default().fg(Color::Cyan)),
+        ]);
+        if scope == app.scope {
+            row.style(Style::default().bg(Color::Rgb(64, 64, 64)))
+        } else {
+            row
+        }
+    }).collect();
+    Table::new(rows, widths)
+}
+
+/// Draws the headline table into `area`, scrolled to keep the selection in view.
+fn draw_headlines(app: &mut App, frame: &mut Frame, area: Rect) {
+    app.scroll_headlines(area.height.saturating_sub(2) as usize);
+    let mut state = TableState::new()
+        .with_offset(app.headline_offset)
+        .with_selected((!app.view.is_empty()).then_some(app.selected_article_index));
+    frame.render_stateful_widget(
+        render_headlines(app)
+        .block(
+            Block::bordered()
+                .title(feed_title(app))
+                .title_alignment(Alignment::Left)
+                .border_type(BorderType::Rounded),
+        ),
+        area,
+        &mut state,
+    );
+}
+
+fn render_headlines(app: &App) -> ratatui::widgets::Table<'_> {
+    let selected_index = app.selected_article_index;
+    let highlight = |text: &str, style: Style| match &app.search {
+        Some(search) => search.highlight(text, style),
+        None => Line::styled(text.to_string(), style),
+    };
     let widths = vec![
         Constraint::Max(3),
+        Constraint::Max(1),
         Constraint::Max(10),
         Constraint::Max(3),
         Constraint::Fill(1),
     ];
-    let rows : Vec<Row> = articles.iter().enumerate().skip(selected_index).take(selected_index + height).map(|(idx, article)| {
+    let rows : Vec<Row> = app.view.iter().map(|&idx| &app.articles[idx]).enumerate().map(|(idx, article)| {
+        let title_style = if article.read {
+            Style::default().fg(Color::Rgb(128,128,128))
+        } else {
+            Style::default().add_modifier(Modifier::BOLD)
+        };
+        let marker = if article.starred {
+            Cell::from("★").style(Style::default().fg(Color::Yellow))
+        } else if !article.read {
+            Cell::from("●").style(Style::default().fg(Color::Cyan))
+        } else {
+            Cell::from(" ")
+        };
         if idx == selected_index  {
             Row::new(vec![
                 Cell::from(idx.to_string()), 
-                Cell::from(article.publisher.to_string()), 
-                Cell::from(time_ago(article.date)),
-                Cell::from(article.title.to_string()).style(Style::default().add_modifier(Modifier::BOLD))
+                marker,
+                Cell::from(highlight(&article.publisher, Style::default())), 
+                Cell::from(time_ago(article.time())),
+                Cell::from(highlight(&article.title, title_style))
             ]).style(Style::default().bg(Color::Rgb(64, 64, 64)))
         } else {
             Row::new(vec![
                 Cell::from(idx.to_string()).style(Style::default().fg(Color::Rgb(128,128,128))), 
-                Cell::from(article.publisher.to_string()), 
-                Cell::from(time_ago(article.date)).style(Style::default().fg(Color::Rgb(128,128,128))),
-                Cell::from(article.title.to_string()).style(Style::default().add_modifier(Modifier::BOLD))
+                marker,
+                Cell::from(highlight(&article.publisher, Style::default())), 
+                Cell::from(time_ago(article.time())).style(Style::default().fg(Color::Rgb(128,128,128))),
+                Cell::from(highlight(&article.title, title_style))
             ])
         }
     }).collect();
     Table::new(rows, widths)
 }
 
-fn render_detail(detail: String, offset: usize) -> ratatui::widgets::Paragraph<'static> {
-    Paragraph::new(detail).wrap(ratatui::widgets::Wrap { trim: false }).scroll((offset as u16, 0))
+fn render_status_bar(app: &App) -> Paragraph<'_> {
+    let feeds = app.config.enabled_feeds();
+    let failing = feeds
+        .iter()
+        .filter(|f| matches!(app.feed_reports.get(&f.url), Some(FeedReport { status: Err(_), .. })))
+        .count();
+    let mut status = format!("{} feeds, {} failing", feeds.len(), failing);
+    if app.last_update_timestamp != 0 {
+        status.push_str(&format!(" | updated {} ago", time_ago(app.last_update_timestamp)));
+    }
+    if app.loading > 0 {
+        status.push_str(" | Loading...");
+    }
+    let message = app.message.as_ref().filter(|(_, at)| Utc::now().timestamp() - at < 10);
+    if let Some((message, _)) = message {
+        status.push_str(&format!(" | {message}"));
+    }
+    let style = if failing > 0 || message.is_some() { Style::default().fg(Color::Red) } else { Style::default().fg(Color::Rgb(128,128,128)) };
+    Paragraph::new(status).style(style)
+}
+
+fn render_feeds(app: &App) -> Table<'_> {
+    let widths = vec![
+        Constraint::Max(1),
+        Constraint::Max(30),
+        Constraint::Fill(1),
+        Constraint::Max(16),
+    ];
+    let header = Row::new(vec!["", "Feed", "Status", "Last success"]).style(Style::default().add_modifier(Modifier::BOLD));
+    let rows: Vec<Row> = app.config.feeds.iter().map(|feed| {
+        let name = app.feed_name(feed);
+        let report = app.feed_reports.get(&feed.url);
+        let (marker, status) = match report.map(|x| &x.status) {
+            _ if !feed.enabled => (Cell::from(" "), "disabled".to_string()),
+            None => (Cell::from(" "), "not loaded yet".to_string()),
+            Some(Ok(items)) => match report.map_or(&[][..], |x| &x.skipped[..]) {
+                [] => (Cell::from("✓").style(Style::default().fg(Color::Green)), format!("{items} articles")),
+                skipped => (
+                    Cell::from("!").style(Style::default().fg(Color::Yellow)),
+                    format!("{items} articles, {} skipped ({})", skipped.len(), skipped[0]),
+                ),
+            },
+            Some(Err(e)) => (Cell::from("✗").style(Style::default().fg(Color::Red)), e.to_string()),
+        };
+        let last_success = report
+            .and_then(|x| x.last_success)
+            .map_or("never".to_string(), |x| format!("{} ago", time_ago(x)));
+        Row::new(vec![marker, Cell::from(name), Cell::from(status), Cell::from(last_success)])
+    }).collect();
+    Table::new(rows, widths).header(header)
+}
+
+fn detail_title(dtl: &AppDetail) -> String {
+    let mut title = if dtl.back.is_empty() { dtl.article.title.clone() } else { dtl.url.clone() };
+    if dtl.loading {
+        title.push_str(" - Loading...");
+    }
+    if dtl.embedded && dtl.back.is_empty() {
+        title.push_str(" - from feed, w for web page");
+    }
+    match (dtl.selected_link, dtl.link_input) {
+        (Some(number), _) => title.push_str(&format!(" - [{number}] {}", dtl.links[number - 1])),
+        (None, Some(number)) => title.push_str(&format!(" - no link [{number}]")),
+        _ => {}
+    }
+    if dtl.search.as_ref().is_some_and(|x| x.pattern.is_some()) {
+        match dtl.hits.len() {
+            0 => title.push_str(" - no matches"),
+            count => title.push_str(&format!(" - match {}/{count}", dtl.hit + 1)),
+        }
+    }
+    title
+}
+
+/// The page with the search matches highlighted, the current one in another colour.
+fn highlight_hits(dtl: &AppDetail) -> Text<'static> {
+    let mut text = dtl.content.clone();
+    let mut idx = 0;
+    while idx < dtl.hits.len() {
+        let line = dtl.hits[idx].0;
+        let mut ranges = Vec::new();
+        while idx < dtl.hits.len() && dtl.hits[idx].0 == line {
+            let style = if idx == dtl.hit {
+                Style::default().fg(Color::Black).bg(Color::LightRed)
+            } else {
+                highlight_style(Style::default())
+            };
+            ranges.push((dtl.hits[idx].1.clone(), style));
+            idx += 1;
+        }
+        text.lines[line] = highlight_ranges(&text.lines[line], &ranges);
+    }
+    text
+}
+
+/// Title, author, categories, publish time and links of `article`, then its summary.
+fn render_preview(article: Option<&Article>, summary: Option<Text<'static>>) -> Paragraph<'static> {
+    let Some(article) = article else { return Paragraph::new("No article selected") };
+    let label = |name: &str, value: String| Line::from(vec![
+        Span::styled(format!("{name}: "), Style::default().fg(Color::Rgb(128,128,128))),
+        Span::raw(value),
+    ]);
+    let format_date = |date: i64| chrono::DateTime::<Utc>::from_timestamp(date, 0)
+        .map_or("unknown".to_string(), |x| x.with_timezone(&Local).format("%a %e %b %Y %H:%M").to_string());
+    let mut lines = vec![Line::styled(article.title.clone(), Style::default().add_modifier(Modifier::BOLD))];
+    if !article.author.is_empty() {
+        lines.push(label("By", format!("{} ({})", article.author, article.publisher)));
+    } else {
+        lines.push(label("From", article.publisher.clone()));
+    }
+    if !article.categories.is_empty() {
+        lines.push(label("Categories", article.categories.join(", ")));
+    }
+    match article.date {
+        Some(date) => lines.push(label("Published", format_date(date))),
+        None => lines.push(label("Published", format!("unknown, first seen {}", format_date(article.first_seen)))),
+    }
+    if let Some(updated) = article.updated.filter(|&x| Some(x) != article.date) {
+        lines.push(label("Updated", format_date(updated)));
+    }
+    let link_style = Style::default().fg(Color::Blue).add_modifier(Modifier::UNDERLINED);
+    lines.push(Line::styled(article.link.clone(), link_style));
+    if let Some(comments) = &article.comments {
+        lines.push(Line::from(vec![
+            Span::styled("Comments: ", Style::default().fg(Color::Rgb(128,128,128))),
+            Span::styled(comments.clone(), link_style),
+        ]));
+    }
+    for enclosure in &article.enclosures {
+        let size = enclosure.length.map_or(String::new(), |x| format!(", {:.1} MB", x as f64 / 1_000_000.0));
+        lines.push(Line::from(vec![
+            Span::styled("Attachment: ", Style::default().fg(Color::Rgb(128,128,128))),
+            Span::styled(enclosure.url.clone(), link_style),
+            Span::raw(format!(" ({}{size})", if enclosure.mime_type.is_empty() { "unknown type" } else { &enclosure.mime_type })),
+        ]));
+    }
+    lines.push(Line::default());
+    match summary {
+        Some(summary) if !article.summary.trim().is_empty() => lines.extend(summary.lines),
+        _ => lines.push(Line::styled("No summary in the feed", Style::default().fg(Color::Rgb(128,128,128)))),
+    }
+    Paragraph::new(lines).wrap(Wrap { trim: false })
+}
+
+fn render_detail(detail: Text<'static>, offset: usize) -> ratatui::widgets::Paragraph<'static> {
+    // Already wrapped to the width of the view by `render::render_html`
+    Paragraph::new(detail).scroll((offset as u16, 0))
+}
+
+fn search_title(search: Option<&Search>, matches: usize) -> String {
+    let Some(search) = search else { return "Search".to_string() };
+    let kind = if search.regex { "Regex search" } else { "Search" };
+    if search.regex && !search.query.is_empty() && search.pattern.is_none() {
+        format!("{kind} - invalid regex (C-r for plain text)")
+    } else {
+        format!("{kind} - {matches} matches (C-r for {})", if search.regex { "plain text" } else { "regex" })
+    }
+}
+
+fn render_search(search: Option<&Search>) -> Paragraph<'static> {
+    let query = search.map_or("", |x| x.query.as_str());
+    Paragraph::new(format!("/{query}"))
 }
 
 fn render_jump(current_jump: usize) -> ratatui::widgets::Paragraph<'static> {